@@ -0,0 +1,245 @@
+use core::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{error, io};
+
+use crate::{capabilities, MemlockLimit};
+
+/// The bytes of secret pages currently held by the platform allocators.
+static LOCKED: AtomicUsize = AtomicUsize::new(0);
+
+/// The per-process quota on locked bytes, where `usize::MAX` stands for
+/// no quota.
+static QUOTA: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// The locked memory budget of the process, returned by [`memlock_budget`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MemlockBudget {
+    /// The bytes of secret pages currently held by the platform allocators.
+    ///
+    /// These pages are locked into RAM, unless [`MlockFailure::Warn`](super::MlockFailure::Warn)
+    /// let an allocation through without locking it. Guard pages are not
+    /// locked, and are not included.
+    pub locked: usize,
+    /// The `RLIMIT_MEMLOCK` resource limit, if the platform has one.
+    pub limit: Option<MemlockLimit>,
+    /// The quota on locked bytes set with [`Config::memlock_quota`](super::Config::memlock_quota),
+    /// if any.
+    pub quota: Option<usize>,
+}
+
+impl MemlockBudget {
+    /// Returns the number of bytes which can still be allocated before
+    /// reaching either the current `RLIMIT_MEMLOCK` limit or the quota, or
+    /// `None` if neither is set.
+    ///
+    /// The locked memory limit is shared with the rest of the process, so
+    /// memory locked outside of this crate (e.g. with `mlockall`) shrinks the
+    /// actual budget without being accounted for here.
+    pub fn remaining(&self) -> Option<u64> {
+        let locked = self.locked as u64;
+        let limit = self.limit.and_then(|limit| limit.current);
+        let quota = self.quota.map(|quota| quota as u64);
+
+        [limit, quota]
+            .into_iter()
+            .flatten()
+            .map(|budget| budget.saturating_sub(locked))
+            .min()
+    }
+}
+
+/// Returns the locked memory budget of the process.
+pub fn memlock_budget() -> MemlockBudget {
+    MemlockBudget {
+        locked: LOCKED.load(Ordering::Relaxed),
+        limit: capabilities::memlock_limit(),
+        quota: match QUOTA.load(Ordering::Relaxed) {
+            usize::MAX => None,
+            quota => Some(quota),
+        },
+    }
+}
+
+/// Attempts to raise the current (soft) `RLIMIT_MEMLOCK` limit up to the
+/// maximum (hard) one, returning the resulting limits.
+///
+/// Raising the soft limit up to the hard one needs no privileges. See
+/// [`Config::raise_memlock_limit`](super::Config::raise_memlock_limit) to do
+/// so when the platform allocator is initialized.
+///
+/// # Errors
+/// Returns an `io::Error` if the limit cannot be read or changed, of kind
+/// `Unsupported` on platforms without `RLIMIT_MEMLOCK`.
+#[cfg(target_family = "unix")]
+pub fn raise_memlock_limit() -> io::Result<MemlockLimit> {
+    let mut rlimit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut rlimit) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    if rlimit.rlim_cur != rlimit.rlim_max {
+        rlimit.rlim_cur = rlimit.rlim_max;
+        if unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &rlimit) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    capabilities::memlock_limit().ok_or_else(io::Error::last_os_error)
+}
+
+/// Attempts to raise the current (soft) `RLIMIT_MEMLOCK` limit up to the
+/// maximum (hard) one, returning the resulting limits.
+///
+/// Raising the soft limit up to the hard one needs no privileges. See
+/// [`Config::raise_memlock_limit`](super::Config::raise_memlock_limit) to do
+/// so when the platform allocator is initialized.
+///
+/// # Errors
+/// Returns an `io::Error` if the limit cannot be read or changed, of kind
+/// `Unsupported` on platforms without `RLIMIT_MEMLOCK`.
+#[cfg(not(target_family = "unix"))]
+pub fn raise_memlock_limit() -> io::Result<MemlockLimit> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "RLIMIT_MEMLOCK is not available on this platform",
+    ))
+}
+
+/// Sets the per-process quota on locked bytes.
+pub(super) fn set_quota(quota: Option<usize>) {
+    QUOTA.store(quota.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// Accounts for `size` bytes of secret pages about to be allocated.
+///
+/// # Errors
+/// Returns a [`QuotaExceeded`] error if the allocation would exceed the quota.
+pub(super) fn reserve(size: usize) -> io::Result<()> {
+    try_reserve(&LOCKED, QUOTA.load(Ordering::Relaxed), size)
+}
+
+/// Accounts for `size` bytes of secret pages that have been released (or
+/// could not be allocated after all).
+pub(super) fn release(size: usize) {
+    LOCKED.fetch_sub(size, Ordering::Relaxed);
+}
+
+fn try_reserve(locked: &AtomicUsize, quota: usize, size: usize) -> io::Result<()> {
+    locked
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |locked| {
+            locked.checked_add(size).filter(|&total| total <= quota)
+        })
+        .map(drop)
+        .map_err(|locked| {
+            let error = QuotaExceeded {
+                requested: size,
+                locked,
+                quota,
+            };
+            io::Error::new(io::ErrorKind::OutOfMemory, error)
+        })
+}
+
+/// Returns `true` if `error` was caused by exceeding the quota.
+pub(crate) fn is_quota_exceeded(error: &io::Error) -> bool {
+    error
+        .get_ref()
+        .is_some_and(|error| error.is::<QuotaExceeded>())
+}
+
+/// The error carried by the `io::Error` of an allocation exceeding the quota.
+#[derive(Debug)]
+struct QuotaExceeded {
+    requested: usize,
+    locked: usize,
+    quota: usize,
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Locked memory quota exceeded ({} bytes requested, {} of {} bytes locked)",
+            self.requested, self.locked, self.quota
+        )
+    }
+}
+
+impl error::Error for QuotaExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_quota() {
+        let locked = AtomicUsize::new(0);
+
+        try_reserve(&locked, 8192, 4096).expect("Reservation within the quota");
+        try_reserve(&locked, 8192, 4096).expect("Reservation within the quota");
+
+        let error = try_reserve(&locked, 8192, 4096).expect_err("Quota should be exceeded");
+        assert!(is_quota_exceeded(&error));
+        assert_eq!(error.kind(), io::ErrorKind::OutOfMemory);
+        assert_eq!(locked.load(Ordering::Relaxed), 8192);
+
+        let error = crate::Error::from_alloc(error);
+        assert!(matches!(error, crate::Error::QuotaExceeded(_)));
+
+        assert!(!is_quota_exceeded(&io::Error::from(
+            io::ErrorKind::OutOfMemory
+        )));
+    }
+
+    #[test]
+    fn test_budget_remaining() {
+        let mut budget = MemlockBudget {
+            locked: 4096,
+            limit: None,
+            quota: None,
+        };
+        assert_eq!(budget.remaining(), None);
+
+        budget.quota = Some(16384);
+        assert_eq!(budget.remaining(), Some(12288));
+
+        budget.limit = Some(MemlockLimit {
+            current: Some(8192),
+            max: None,
+        });
+        assert_eq!(budget.remaining(), Some(4096));
+
+        budget.locked = 32768;
+        assert_eq!(budget.remaining(), Some(0));
+    }
+
+    #[test]
+    fn test_budget_tracking() {
+        // Raising the limit affects the whole process
+        if !crate::util::isolate("alloc::budget::tests::test_budget_tracking", &[]) {
+            return;
+        }
+
+        let alloc = crate::alloc::platform_secret_allocator();
+        let layout = core::alloc::Layout::new::<[u8; 32]>();
+
+        let before = memlock_budget().locked;
+        let ptr = alloc.alloc(layout).expect("Failed to allocate");
+        assert_eq!(memlock_budget().locked, before + crate::alloc::page_size());
+        unsafe { alloc.dealloc(ptr, layout) }.expect("Failed to deallocate");
+        assert_eq!(memlock_budget().locked, before);
+
+        #[cfg(target_family = "unix")]
+        {
+            let limit = raise_memlock_limit().expect("Failed to raise RLIMIT_MEMLOCK");
+            assert_eq!(limit.current, limit.max);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use core::ptr::{self, NonNull};
+use std::{io, process, sync::OnceLock};
+
+use super::{
+    config::{CanaryFailure, ForkPolicy},
+    process_secret_allocator,
+    util::CANARY_SIZE,
+    SecretAllocator,
+};
+use crate::{
+    marker::Locked,
+    util::{ct, random},
+    SecretBox,
+};
+
+/// The per-process canary, along with the id of the process which generated
+/// it.
+struct Canary {
+    bytes: SecretBox<[u8; CANARY_SIZE], Locked, &'static dyn SecretAllocator>,
+    pid: u32,
+}
+
+/// The per-process canary, generated on first use and kept in read-only
+/// secret memory for the lifetime of the process, following the
+/// configuration of the platform allocator.
+///
+/// It is inherited by forked children whatever the [`ForkPolicy`] of the
+/// regions it guards, so that they can still allocate and check regions of
+/// their own. It is allocated without a canary, which would otherwise need
+/// itself.
+static CANARY: OnceLock<Canary> = OnceLock::new();
+
+/// Returns the per-process canary, generating it on first use.
+///
+/// # Errors
+/// Returns an `io::Error` if the canary cannot be allocated or generated.
+fn canary() -> io::Result<&'static Canary> {
+    if let Some(canary) = CANARY.get() {
+        return Ok(canary);
+    }
+
+    let mut bytes = SecretBox::try_new_in([0; CANARY_SIZE], process_secret_allocator()?)?;
+    random::fill_random(&mut *bytes)?;
+    let canary = Canary {
+        bytes: bytes.lock()?,
+        pid: process::id(),
+    };
+
+    // Another thread may have generated the canary in the meantime, in which
+    // case this one is dropped
+    Ok(CANARY.get_or_init(|| canary))
+}
+
+/// Writes the canary in front of the user region starting at `ptr`.
+///
+/// # Errors
+/// Returns an `io::Error` if the canary cannot be allocated or generated.
+///
+/// # Safety
+/// The `CANARY_SIZE` bytes in front of `ptr` must be valid for writes.
+pub unsafe fn write(ptr: NonNull<u8>) -> io::Result<()> {
+    let canary: &[u8; CANARY_SIZE] = &canary()?.bytes;
+    unsafe {
+        ptr::copy_nonoverlapping(canary.as_ptr(), ptr.as_ptr().sub(CANARY_SIZE), CANARY_SIZE)
+    };
+
+    Ok(())
+}
+
+/// Checks the canary in front of the user region starting at `ptr`, which
+/// follows `fork_policy`, responding to a mismatch according to `policy`.
+///
+/// In a forked child, the zero-filled canary of a region wiped on fork is
+/// accepted.
+///
+/// # Errors
+/// Returns an `io::Error` of kind `InvalidData` if the canary does not match,
+/// and the `policy` neither aborts nor panics, or any `io::Error` raised while
+/// generating the canary.
+///
+/// # Safety
+/// The `CANARY_SIZE` bytes in front of `ptr` must be valid for reads.
+pub unsafe fn check(
+    ptr: NonNull<u8>,
+    policy: CanaryFailure,
+    fork_policy: ForkPolicy,
+) -> io::Result<()> {
+    let found = unsafe { &*ptr::slice_from_raw_parts(ptr.as_ptr().sub(CANARY_SIZE), CANARY_SIZE) };
+    let canary = canary()?;
+    if ct::ct_eq(found, &*canary.bytes) {
+        return Ok(());
+    }
+
+    // The regions inherited by a forked child are zero-filled, canary included
+    if fork_policy == ForkPolicy::WipeOnFork
+        && canary.pid != process::id()
+        && found.iter().all(|&byte| byte == 0)
+    {
+        return Ok(());
+    }
+
+    const MESSAGE: &str = "Secret memory canary mismatch, the memory has been corrupted";
+    match policy {
+        CanaryFailure::Abort => process::abort(),
+        CanaryFailure::Panic => panic!("{MESSAGE}"),
+        CanaryFailure::Callback(callback) => {
+            callback();
+            Err(io::Error::new(io::ErrorKind::InvalidData, MESSAGE))
+        }
+    }
+}
@@ -0,0 +1,679 @@
+use core::{alloc::Layout, fmt, ptr::NonNull, str::FromStr};
+use std::{env, io};
+
+#[cfg(target_os = "linux")]
+use super::LinuxSecretAllocator;
+#[cfg(target_family = "unix")]
+use super::UnixSecretAllocator;
+#[cfg(target_family = "windows")]
+use super::WindowsSecretAllocator;
+use super::{budget, PlatformInstance, SecretAllocator};
+use crate::stats;
+
+/// Name of the environment variable selecting the [`Backend`].
+pub const BACKEND_ENV: &str = "SECRET_MEM_BACKEND";
+
+/// Name of the environment variable selecting the [`MlockFailure`] policy.
+pub const MLOCK_FAILURE_ENV: &str = "SECRET_MEM_MLOCK_FAILURE";
+
+/// Name of the environment variable selecting the [`ForkPolicy`].
+pub const FORK_POLICY_ENV: &str = "SECRET_MEM_FORK_POLICY";
+
+/// The backend used by the platform secret memory allocator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Uses `memfd_secret` when available, falling back to `mlock`ed
+    /// anonymous mappings otherwise (`memfd`).
+    #[default]
+    Memfd,
+    /// Always uses `mlock`ed anonymous mappings (`mlock`), or `VirtualLock`ed
+    /// pages on Windows.
+    Mlock,
+    /// Requires `memfd_secret`, failing every allocation when it is not
+    /// available (`strict-memfd`).
+    StrictMemfd,
+}
+
+impl FromStr for Backend {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "memfd" => Ok(Self::Memfd),
+            "mlock" => Ok(Self::Mlock),
+            "strict-memfd" => Ok(Self::StrictMemfd),
+            _ => Err(invalid_value(BACKEND_ENV, s)),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Memfd => "memfd",
+            Self::Mlock => "mlock",
+            Self::StrictMemfd => "strict-memfd",
+        })
+    }
+}
+
+/// The kind of allocator picked by the platform secret memory allocator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllocatorKind {
+    /// The [`LinuxSecretAllocator`](super::LinuxSecretAllocator), backed by
+    /// `memfd_secret`.
+    MemfdSecret,
+    /// The [`UnixSecretAllocator`](super::UnixSecretAllocator), backed by
+    /// `mlock`ed anonymous mappings.
+    Mlock,
+    /// The [`WindowsSecretAllocator`](super::WindowsSecretAllocator), backed by
+    /// `VirtualLock`ed pages.
+    VirtualLock,
+    /// No allocator, since the configuration could not be satisfied.
+    /// Every allocation fails.
+    Unavailable,
+}
+
+impl fmt::Display for AllocatorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MemfdSecret => "memfd_secret",
+            Self::Mlock => "mlock",
+            Self::VirtualLock => "VirtualLock",
+            Self::Unavailable => "unavailable",
+        })
+    }
+}
+
+/// The behavior of the allocators when the memory cannot be locked
+/// into RAM (e.g. because `RLIMIT_MEMLOCK` has been reached).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MlockFailure {
+    /// Fails the allocation (`fatal`).
+    #[default]
+    Fatal,
+    /// Keeps the memory unlocked (`warn`), which may then be swapped to disk.
+    /// Such allocations are counted by
+    /// [`BackendStats::unlocked_allocs`](crate::BackendStats::unlocked_allocs).
+    Warn,
+}
+
+impl FromStr for MlockFailure {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fatal" => Ok(Self::Fatal),
+            "warn" => Ok(Self::Warn),
+            _ => Err(invalid_value(MLOCK_FAILURE_ENV, s)),
+        }
+    }
+}
+
+impl fmt::Display for MlockFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Fatal => "fatal",
+            Self::Warn => "warn",
+        })
+    }
+}
+
+/// The visibility of secret pages in the children of a forking process,
+/// on Unix.
+///
+/// Private anonymous mappings (the `mlock` backend) are copied into the
+/// children, keys included, unless told otherwise. `memfd_secret` mappings
+/// are shared (`MAP_SHARED`) instead: the children access the very same
+/// pages as their parent, and writes on either side are visible to the
+/// other. Wiping on fork only applies to private anonymous mappings, so
+/// `memfd_secret` mappings are never inherited unless the policy is
+/// [`ForkPolicy::Inherit`].
+///
+/// Children must not touch the secrets they did not inherit, which either
+/// read as zeros or fault. Secrets are wiped with `MADV_WIPEONFORK` on Linux
+/// 4.14 or later, and with `minherit` on FreeBSD, NetBSD and OpenBSD. They are
+/// left out with `MADV_DONTFORK` on Linux, and with `minherit` on the BSDs and
+/// on macOS. Other Unix systems inherit every secret.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ForkPolicy {
+    /// Lets the children inherit the secret pages (`inherit`).
+    Inherit,
+    /// Zero-fills the secret pages of the children (`wipe`), leaving them
+    /// out wherever wiping is not supported (e.g. `memfd_secret` mappings,
+    /// or Linux before 4.14).
+    #[default]
+    WipeOnFork,
+    /// Leaves the secret pages out of the children (`dont-fork`), where
+    /// accessing them faults.
+    DontFork,
+}
+
+impl FromStr for ForkPolicy {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inherit" => Ok(Self::Inherit),
+            "wipe" => Ok(Self::WipeOnFork),
+            "dont-fork" => Ok(Self::DontFork),
+            _ => Err(invalid_value(FORK_POLICY_ENV, s)),
+        }
+    }
+}
+
+impl fmt::Display for ForkPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Inherit => "inherit",
+            Self::WipeOnFork => "wipe",
+            Self::DontFork => "dont-fork",
+        })
+    }
+}
+
+/// The response to a canary mismatch, which reveals that the memory in
+/// front of a secret has been overwritten (e.g. by a buffer underflow).
+#[derive(Clone, Copy, Debug, Default)]
+pub enum CanaryFailure {
+    /// Aborts the process.
+    #[default]
+    Abort,
+    /// Panics.
+    Panic,
+    /// Calls the given function, then fails the operation which detected
+    /// the mismatch with an `InvalidData` error.
+    Callback(fn()),
+}
+
+impl fmt::Display for CanaryFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Abort => "abort",
+            Self::Panic => "panic",
+            Self::Callback(_) => "callback",
+        })
+    }
+}
+
+/// Configuration of the platform secret memory allocator.
+///
+/// Unless a configuration is [installed](Config::install) before the first
+/// allocation, the platform allocator is configured from the environment,
+/// see [`Config::from_env`].
+///
+/// # Example
+/// ```
+/// use secret_mem::alloc::{Backend, Config, MlockFailure};
+///
+/// let result = Config::new()
+///     .backend(Backend::Mlock)
+///     .mlock_failure(MlockFailure::Warn)
+///     .install();
+/// # let _ = result;
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    backend: Backend,
+    mlock_failure: MlockFailure,
+    guard_pages: bool,
+    canaries: bool,
+    canary_failure: CanaryFailure,
+    fork_policy: ForkPolicy,
+    memlock_quota: Option<usize>,
+    raise_memlock_limit: bool,
+}
+
+impl Config {
+    /// Creates a new `Config` with the default options.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new `Config` from the `SECRET_MEM_BACKEND`
+    /// (`memfd`, `mlock` or `strict-memfd`), `SECRET_MEM_MLOCK_FAILURE`
+    /// (`fatal` or `warn`) and `SECRET_MEM_FORK_POLICY` (`inherit`, `wipe` or
+    /// `dont-fork`) environment variables, using the default options for
+    /// those which are not set.
+    ///
+    /// Unless another configuration is installed, the platform allocator is
+    /// configured from the environment when it is first used. If a variable
+    /// then holds an unknown value, every allocation fails: call
+    /// `Config::from_env()?.install()` at startup to handle the error instead.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` of kind `InvalidInput` if a variable holds
+    /// an unknown value.
+    pub fn from_env() -> io::Result<Self> {
+        let mut config = Self::new();
+
+        if let Some(backend) = env_var(BACKEND_ENV)? {
+            config.backend = backend.parse()?;
+        }
+        if let Some(mlock_failure) = env_var(MLOCK_FAILURE_ENV)? {
+            config.mlock_failure = mlock_failure.parse()?;
+        }
+        if let Some(fork_policy) = env_var(FORK_POLICY_ENV)? {
+            config.fork_policy = fork_policy.parse()?;
+        }
+
+        Ok(config)
+    }
+
+    /// Sets the backend of the platform allocator.
+    #[inline]
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets the behavior of the allocators when the memory cannot be locked.
+    #[inline]
+    pub fn mlock_failure(mut self, mlock_failure: MlockFailure) -> Self {
+        self.mlock_failure = mlock_failure;
+        self
+    }
+
+    /// Enables or disables the inaccessible guard pages placed before and
+    /// after each allocation, so that linear overflows from (or into)
+    /// neighbouring memory fault right away.
+    ///
+    /// With guard pages, the secret is right-aligned against the trailing
+    /// guard page. Each allocation then maps two more pages, which are neither
+    /// locked nor taken from the secret memory. Guard pages are only
+    /// supported on Unix, and are disabled by default.
+    #[inline]
+    pub fn guard_pages(mut self, enabled: bool) -> Self {
+        self.guard_pages = enabled;
+        self
+    }
+
+    /// Enables or disables the random canary stored in front of each
+    /// allocation, which is checked whenever the memory is made read-only
+    /// or writable, and when it is deallocated.
+    ///
+    /// The canary catches underflows from neighbouring memory, including
+    /// those landing in the padding left before a right-aligned secret
+    /// that guard pages alone miss. Canaries are only supported on Unix,
+    /// and are disabled by default.
+    #[inline]
+    pub fn canaries(mut self, enabled: bool) -> Self {
+        self.canaries = enabled;
+        self
+    }
+
+    /// Sets the response to a canary mismatch.
+    #[inline]
+    pub fn canary_failure(mut self, canary_failure: CanaryFailure) -> Self {
+        self.canary_failure = canary_failure;
+        self
+    }
+
+    /// Sets the visibility of secret pages in the children of a forking
+    /// process, see [`ForkPolicy`]. Defaults to [`ForkPolicy::WipeOnFork`].
+    #[inline]
+    pub fn fork_policy(mut self, fork_policy: ForkPolicy) -> Self {
+        self.fork_policy = fork_policy;
+        self
+    }
+
+    /// Sets a per-process quota, in bytes, on the locked memory held by the
+    /// platform allocators (see [`MemlockBudget::locked`](super::MemlockBudget::locked)).
+    ///
+    /// The quota counts whole pages, as `RLIMIT_MEMLOCK` does, not the bytes
+    /// requested by each secret: a 32-byte key uses up a page of it, unless
+    /// it is packed by a [`SlabSecretAllocator`](super::SlabSecretAllocator).
+    /// Allocations which would exceed the quota fail with an error classified
+    /// as [`Error::QuotaExceeded`](crate::Error::QuotaExceeded), before the
+    /// locked memory limit of the system is reached. The quota applies to the
+    /// whole process once this configuration is installed, and is unset by
+    /// default.
+    #[inline]
+    pub fn memlock_quota(mut self, quota: usize) -> Self {
+        self.memlock_quota = Some(quota);
+        self
+    }
+
+    /// Enables or disables the attempt to raise the current (soft)
+    /// `RLIMIT_MEMLOCK` limit up to the maximum (hard) one when the platform
+    /// allocator is initialized, see [`raise_memlock_limit`](super::raise_memlock_limit).
+    ///
+    /// A failed attempt is ignored. The attempt is disabled by default.
+    #[inline]
+    pub fn raise_memlock_limit(mut self, enabled: bool) -> Self {
+        self.raise_memlock_limit = enabled;
+        self
+    }
+
+    /// Returns the configured backend.
+    #[inline]
+    pub fn get_backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Returns the configured behavior when the memory cannot be locked.
+    #[inline]
+    pub fn get_mlock_failure(&self) -> MlockFailure {
+        self.mlock_failure
+    }
+
+    /// Returns whether guard pages are enabled.
+    #[inline]
+    pub fn get_guard_pages(&self) -> bool {
+        self.guard_pages
+    }
+
+    /// Returns whether canaries are enabled.
+    #[inline]
+    pub fn get_canaries(&self) -> bool {
+        self.canaries
+    }
+
+    /// Returns the configured response to a canary mismatch.
+    #[inline]
+    pub fn get_canary_failure(&self) -> CanaryFailure {
+        self.canary_failure
+    }
+
+    /// Returns the configured visibility of secret pages in children.
+    #[inline]
+    pub fn get_fork_policy(&self) -> ForkPolicy {
+        self.fork_policy
+    }
+
+    /// Returns the configured quota on locked bytes, if any.
+    #[inline]
+    pub fn get_memlock_quota(&self) -> Option<usize> {
+        self.memlock_quota
+    }
+
+    /// Returns whether the soft `RLIMIT_MEMLOCK` limit is raised on
+    /// initialization.
+    #[inline]
+    pub fn get_raise_memlock_limit(&self) -> bool {
+        self.raise_memlock_limit
+    }
+
+    /// Installs this configuration for the platform secret memory allocator.
+    ///
+    /// The configuration takes precedence over the environment, whose
+    /// variables are still checked so that a mistyped value does not go
+    /// unnoticed.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` of kind:
+    /// - `InvalidInput`, if an environment variable read by
+    ///   [`Config::from_env`] holds an unknown value.
+    /// - `Unsupported`, if the backend is [`Backend::StrictMemfd`] and
+    ///   `memfd_secret` is not available.
+    /// - `AlreadyExists`, if the platform allocator has already been
+    ///   initialized (i.e. a secret has already been allocated).
+    pub fn install(self) -> io::Result<()> {
+        Self::from_env()?;
+        let (kind, alloc) = self.build()?;
+        let instance = PlatformInstance {
+            kind,
+            config: Some(self.clone()),
+            alloc,
+        };
+
+        super::PLATFORM.set(instance).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "Secret memory allocator already initialized",
+            )
+        })?;
+
+        self.apply_limits();
+        Ok(())
+    }
+
+    /// Applies the process-wide locked memory settings of this configuration,
+    /// once it has become the one of the platform allocator.
+    pub(super) fn apply_limits(&self) {
+        if self.raise_memlock_limit {
+            let _ = super::raise_memlock_limit();
+        }
+        budget::set_quota(self.memlock_quota);
+    }
+
+    /// Builds the platform allocator described by this configuration.
+    pub(super) fn build(&self) -> io::Result<(AllocatorKind, Box<dyn SecretAllocator>)> {
+        #[cfg(target_os = "linux")]
+        {
+            let memfd_available = || match unsafe { libc::syscall(libc::SYS_memfd_secret, 0) } {
+                -1 => false,
+                fd => {
+                    unsafe { libc::close(fd as libc::c_int) };
+                    true
+                }
+            };
+
+            match self.backend {
+                Backend::Memfd | Backend::StrictMemfd if memfd_available() => Ok((
+                    AllocatorKind::MemfdSecret,
+                    Box::new(LinuxSecretAllocator::with_config(self)),
+                )),
+                Backend::StrictMemfd => Err(memfd_unavailable()),
+                Backend::Memfd | Backend::Mlock => Ok((
+                    AllocatorKind::Mlock,
+                    Box::new(UnixSecretAllocator::with_config(self)),
+                )),
+            }
+        }
+
+        #[cfg(all(target_family = "unix", not(target_os = "linux")))]
+        {
+            match self.backend {
+                Backend::StrictMemfd => Err(memfd_unavailable()),
+                Backend::Memfd | Backend::Mlock => Ok((
+                    AllocatorKind::Mlock,
+                    Box::new(UnixSecretAllocator::with_config(self)),
+                )),
+            }
+        }
+
+        #[cfg(target_family = "windows")]
+        {
+            match self.backend {
+                Backend::StrictMemfd => Err(memfd_unavailable()),
+                Backend::Memfd | Backend::Mlock => Ok((
+                    AllocatorKind::VirtualLock,
+                    Box::new(WindowsSecretAllocator::with_config(self)),
+                )),
+            }
+        }
+    }
+}
+
+/// Stand-in for a platform allocator which could not be configured, failing
+/// every allocation with the configuration error.
+pub(super) struct Unavailable(String);
+
+impl Unavailable {
+    pub(super) fn new(error: io::Error) -> Self {
+        Self(format!("Secret memory allocator unavailable: {error}"))
+    }
+
+    fn error(&self) -> io::Error {
+        io::Error::new(io::ErrorKind::Unsupported, self.0.as_str())
+    }
+}
+
+unsafe impl SecretAllocator for Unavailable {
+    fn alloc(&self, _: Layout) -> io::Result<NonNull<u8>> {
+        Err(self.error())
+    }
+
+    unsafe fn make_read_only(&self, _: NonNull<u8>, _: Layout) -> io::Result<()> {
+        Err(self.error())
+    }
+
+    unsafe fn make_writable(&self, _: NonNull<u8>, _: Layout) -> io::Result<()> {
+        Err(self.error())
+    }
+
+    unsafe fn dealloc(&self, _: NonNull<u8>, _: Layout) -> io::Result<()> {
+        Err(self.error())
+    }
+}
+
+/// Handles a failure to lock the memory into RAM according to `policy`,
+/// returning the error if the allocation must fail, or recording the
+/// degradation in the `counters` of the backend otherwise.
+pub(super) fn handle_mlock_failure(
+    policy: MlockFailure,
+    error: io::Error,
+    counters: &stats::Counters,
+) -> io::Result<()> {
+    match policy {
+        MlockFailure::Fatal => Err(error),
+        MlockFailure::Warn => {
+            counters.record_unlocked();
+            Ok(())
+        }
+    }
+}
+
+/// Reads an environment variable, treating an unset variable as `None`.
+fn env_var(name: &str) -> io::Result<Option<String>> {
+    match env::var(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(env::VarError::NotUnicode(_)) => Err(invalid_value(name, "<non-unicode>")),
+    }
+}
+
+fn invalid_value(name: &str, value: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("Invalid value \"{value}\" for {name}"),
+    )
+}
+
+fn memfd_unavailable() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "memfd_secret is not available")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_parse() {
+        assert_eq!("memfd".parse::<Backend>().unwrap(), Backend::Memfd);
+        assert_eq!("mlock".parse::<Backend>().unwrap(), Backend::Mlock);
+        assert_eq!(
+            "strict-memfd".parse::<Backend>().unwrap(),
+            Backend::StrictMemfd
+        );
+        assert!("secret".parse::<Backend>().is_err());
+
+        assert_eq!(
+            "fatal".parse::<MlockFailure>().unwrap(),
+            MlockFailure::Fatal
+        );
+        assert_eq!("warn".parse::<MlockFailure>().unwrap(), MlockFailure::Warn);
+        assert!("ignore".parse::<MlockFailure>().is_err());
+
+        for policy in [
+            ForkPolicy::Inherit,
+            ForkPolicy::WipeOnFork,
+            ForkPolicy::DontFork,
+        ] {
+            assert_eq!(policy.to_string().parse::<ForkPolicy>().unwrap(), policy);
+        }
+        assert!("exec".parse::<ForkPolicy>().is_err());
+
+        for backend in [Backend::Memfd, Backend::Mlock, Backend::StrictMemfd] {
+            assert_eq!(backend.to_string().parse::<Backend>().unwrap(), backend);
+        }
+    }
+
+    #[test]
+    fn test_config_build() {
+        let config = Config::new()
+            .backend(Backend::Mlock)
+            .mlock_failure(MlockFailure::Warn);
+        assert_eq!(config.get_backend(), Backend::Mlock);
+        assert_eq!(config.get_mlock_failure(), MlockFailure::Warn);
+
+        let (kind, alloc) = config.build().expect("Failed to build the allocator");
+        #[cfg(target_family = "unix")]
+        assert_eq!(kind, AllocatorKind::Mlock);
+        #[cfg(target_family = "windows")]
+        assert_eq!(kind, AllocatorKind::VirtualLock);
+
+        let layout = core::alloc::Layout::new::<[u8; 32]>();
+        let ptr = alloc.alloc(layout).expect("Failed to allocate");
+        unsafe { alloc.dealloc(ptr, layout) }.expect("Failed to deallocate");
+
+        #[cfg(not(target_os = "linux"))]
+        assert!(Config::new().backend(Backend::StrictMemfd).build().is_err());
+    }
+
+    #[test]
+    fn test_config_install() {
+        // The platform allocator is configured once per process
+        if !crate::util::isolate("alloc::config::tests::test_config_install", &[]) {
+            return;
+        }
+
+        let config = Config::new()
+            .backend(Backend::Memfd)
+            .guard_pages(true)
+            .canaries(true);
+        config
+            .install()
+            .expect("Failed to install the configuration");
+
+        // Whichever backend is picked, it follows the configuration
+        let alloc = super::super::platform_secret_allocator();
+        let layout = Layout::new::<[u8; 32]>();
+        assert_eq!(alloc.allocated_size(layout), 3 * super::super::page_size());
+
+        let ptr = alloc.alloc(layout).expect("Failed to allocate");
+        let end = ptr.as_ptr() as usize + layout.size();
+        assert_eq!(
+            end % super::super::page_size(),
+            0,
+            "Guard pages are missing"
+        );
+
+        // And so does the canary, which is allocated along
+        let stats = crate::stats();
+        let stats = stats.backend(crate::capabilities().allocator);
+        let stats = stats.expect("No statistics for the backend");
+        assert_eq!(stats.allocations, 2);
+        assert_eq!(stats.mapped_bytes, 6 * super::super::page_size());
+        unsafe { alloc.dealloc(ptr, layout) }.expect("Failed to deallocate");
+    }
+
+    #[test]
+    fn test_config_install_invalid_env() {
+        let envs = [(BACKEND_ENV, "strcit-memfd")];
+        if !crate::util::isolate(
+            "alloc::config::tests::test_config_install_invalid_env",
+            &envs,
+        ) {
+            return;
+        }
+
+        // A mistyped variable is reported, and nothing is installed
+        let error = Config::new().install().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error.to_string().contains(BACKEND_ENV));
+        assert!(super::super::PLATFORM.get().is_none());
+    }
+
+    #[test]
+    fn test_config_unavailable() {
+        let alloc = Unavailable::new(memfd_unavailable());
+        let error = alloc
+            .alloc(core::alloc::Layout::new::<u64>())
+            .expect_err("Unavailable allocator should fail");
+        assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+        assert!(error.to_string().contains("memfd_secret is not available"));
+    }
+}
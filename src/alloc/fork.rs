@@ -0,0 +1,105 @@
+use std::io;
+
+use super::config::ForkPolicy;
+
+/// Applies `policy` to the mapping of `len` bytes starting at `addr`.
+///
+/// Shared mappings cannot be wiped on fork, and are left out of the
+/// children instead.
+///
+/// # Safety
+/// `addr` and `len` must describe a mapping owned by the caller.
+pub unsafe fn apply(
+    addr: *mut libc::c_void,
+    len: usize,
+    policy: ForkPolicy,
+    shared: bool,
+) -> io::Result<()> {
+    match policy {
+        ForkPolicy::Inherit => Ok(()),
+        ForkPolicy::WipeOnFork if !shared => match unsafe { wipe_on_fork(addr, len) } {
+            // Older kernels reject the advice
+            Err(e) if e.raw_os_error() == Some(libc::EINVAL) => unsafe { dont_fork(addr, len) },
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => unsafe { dont_fork(addr, len) },
+            result => result,
+        },
+        ForkPolicy::WipeOnFork | ForkPolicy::DontFork => unsafe { dont_fork(addr, len) },
+    }
+}
+
+/// Zero-fills the mapping in the children.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn wipe_on_fork(addr: *mut libc::c_void, len: usize) -> io::Result<()> {
+    check(unsafe { libc::madvise(addr, len, libc::MADV_WIPEONFORK) })
+}
+
+/// Zero-fills the mapping in the children.
+#[cfg(target_os = "freebsd")]
+unsafe fn wipe_on_fork(addr: *mut libc::c_void, len: usize) -> io::Result<()> {
+    check(unsafe { libc::minherit(addr, len, libc::INHERIT_ZERO) })
+}
+
+/// Zero-fills the mapping in the children.
+#[cfg(any(target_os = "netbsd", target_os = "openbsd"))]
+unsafe fn wipe_on_fork(addr: *mut libc::c_void, len: usize) -> io::Result<()> {
+    check(unsafe { libc::minherit(addr, len, libc::MAP_INHERIT_ZERO) })
+}
+
+/// Zero-fills the mapping in the children.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+)))]
+unsafe fn wipe_on_fork(_: *mut libc::c_void, _: usize) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Leaves the mapping out of the children.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn dont_fork(addr: *mut libc::c_void, len: usize) -> io::Result<()> {
+    check(unsafe { libc::madvise(addr, len, libc::MADV_DONTFORK) })
+}
+
+/// Leaves the mapping out of the children.
+#[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+unsafe fn dont_fork(addr: *mut libc::c_void, len: usize) -> io::Result<()> {
+    check(unsafe { libc::minherit(addr, len, libc::INHERIT_NONE) })
+}
+
+/// Leaves the mapping out of the children.
+#[cfg(any(target_os = "netbsd", target_os = "openbsd"))]
+unsafe fn dont_fork(addr: *mut libc::c_void, len: usize) -> io::Result<()> {
+    check(unsafe { libc::minherit(addr, len, libc::MAP_INHERIT_NONE) })
+}
+
+/// Leaves the mapping out of the children.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+unsafe fn dont_fork(addr: *mut libc::c_void, len: usize) -> io::Result<()> {
+    check(unsafe { libc::minherit(addr, len, libc::VM_INHERIT_NONE) })
+}
+
+/// Leaves the mapping out of the children, which other systems have no
+/// way to do.
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "macos",
+    target_os = "ios"
+)))]
+unsafe fn dont_fork(_: *mut libc::c_void, _: usize) -> io::Result<()> {
+    Ok(())
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    match result {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
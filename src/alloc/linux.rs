@@ -1,28 +1,47 @@
-use core::{alloc::Layout, ptr};
+use core::{alloc::Layout, ptr, ptr::NonNull};
 use std::io;
 
-use libc::{SYS_memfd_secret, MAP_FAILED, MAP_SHARED, PROT_READ, PROT_WRITE};
+use libc::{
+    SYS_memfd_secret, MAP_ANON, MAP_FAILED, MAP_FIXED, MAP_PRIVATE, MAP_SHARED, PROT_NONE,
+    PROT_READ, PROT_WRITE,
+};
 use zeroize::Zeroize;
 
-use super::{util, SecretAllocator};
+use super::{budget, canary, config::Config, fork, util, SecretAllocator};
+use crate::stats;
 
-// FIXME Current implementation wastes memory
 /// Provides an implementation of the `SecretAllocator` trait for Linux systems.
 ///
 /// This implementation relies on Linux `SYS_memfd_secret` and Unix system calls
 /// to manage memory in a way that limits its visibility to other processes and
 /// prevents sensitive data from being leaked.
-pub struct LinuxSecretAllocator(());
+///
+/// The `memfd_secret` pages are `MAP_SHARED` mappings, which forked children
+/// would share with their parent: unless the [`ForkPolicy`](super::ForkPolicy)
+/// is [`ForkPolicy::Inherit`](super::ForkPolicy::Inherit), they are left out of the children with
+/// `MADV_DONTFORK`.
+pub struct LinuxSecretAllocator {
+    regions: util::Regions,
+}
 
 impl LinuxSecretAllocator {
+    /// Creates a new `LinuxSecretAllocator`.
     pub fn new() -> Self {
-        Self(())
+        Self::with_config(&Config::new())
+    }
+
+    /// Creates a new `LinuxSecretAllocator` following the given configuration.
+    pub fn with_config(config: &Config) -> Self {
+        Self {
+            regions: util::Regions::with_config(config),
+        }
     }
-}
 
-impl SecretAllocator for LinuxSecretAllocator {
-    fn alloc(&self, layout: Layout) -> io::Result<*mut u8> {
-        let size = util::aligned_layout_size(&layout);
+    /// Maps and sets up the pages holding a user region of the given layout.
+    fn map(&self, layout: Layout) -> io::Result<NonNull<u8>> {
+        let size = self.regions.placement.pages_size(&layout);
+        let guard_size = self.regions.placement.guard_size();
+        let mapping_size = size + 2 * guard_size;
 
         let fd = match unsafe { libc::syscall(SYS_memfd_secret, 0) } {
             -1 => return Err(io::Error::last_os_error()),
@@ -35,98 +54,242 @@ impl SecretAllocator for LinuxSecretAllocator {
             return Err(last_os_error);
         }
 
-        let mmap = unsafe {
-            libc::mmap(
-                ptr::null_mut(),
-                size,
-                PROT_WRITE | PROT_READ,
-                MAP_SHARED,
-                fd,
-                0,
-            )
+        // With guard pages, an inaccessible anonymous span is reserved first,
+        // and the secret pages are then mapped over its middle. Only the pages
+        // holding the user region are thus taken from the secret memory.
+        let span = match self.regions.placement.guard_pages {
+            true => unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    mapping_size,
+                    PROT_NONE,
+                    MAP_PRIVATE | MAP_ANON,
+                    -1,
+                    0,
+                )
+            },
+            false => ptr::null_mut(),
         };
 
+        if span == MAP_FAILED {
+            let last_os_error = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            return Err(last_os_error);
+        }
+
+        let (addr, flags) = match self.regions.placement.guard_pages {
+            true => (
+                unsafe { span.cast::<u8>().add(guard_size) } as _,
+                MAP_SHARED | MAP_FIXED,
+            ),
+            false => (ptr::null_mut(), MAP_SHARED),
+        };
+
+        let mmap = unsafe { libc::mmap(addr, size, PROT_WRITE | PROT_READ, flags, fd, 0) };
+
         let result = match mmap {
-            MAP_FAILED => Err(io::Error::last_os_error()),
-            ptr => Ok(ptr as _),
+            MAP_FAILED => {
+                let last_os_error = io::Error::last_os_error();
+                if self.regions.placement.guard_pages {
+                    unsafe { libc::munmap(span, mapping_size) };
+                }
+                Err(last_os_error)
+            }
+            pages => {
+                let mapping = match self.regions.placement.guard_pages {
+                    true => span,
+                    false => pages,
+                };
+
+                // `memfd_secret` pages are shared with the children unless
+                // they are left out of them.
+                match unsafe { fork::apply(mapping, mapping_size, self.regions.fork_policy, true) }
+                {
+                    Err(error) => {
+                        unsafe { libc::munmap(mapping, mapping_size) };
+                        Err(error)
+                    }
+                    Ok(_) => {
+                        let offset = self.regions.placement.region_offset(&layout);
+                        let ptr = unsafe { NonNull::new_unchecked(pages.cast::<u8>().add(offset)) };
+                        let canary_result = match self.regions.placement.canary {
+                            true => unsafe { canary::write(ptr) },
+                            false => Ok(()),
+                        };
+
+                        match canary_result {
+                            Err(error) => {
+                                unsafe { libc::munmap(mapping, mapping_size) };
+                                Err(error)
+                            }
+                            Ok(_) => Ok(ptr),
+                        }
+                    }
+                }
+            }
         };
 
         unsafe { libc::close(fd) };
         result
     }
+}
+
+impl Default for LinuxSecretAllocator {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl SecretAllocator for LinuxSecretAllocator {
+    fn alloc(&self, layout: Layout) -> io::Result<NonNull<u8>> {
+        let size = self.regions.placement.pages_size(&layout);
+        budget::reserve(size)?;
+
+        let result = self.map(layout).map_err(|error| {
+            budget::release(size);
+            error
+        });
+
+        stats::MEMFD_SECRET.record_alloc(&result, layout.size(), size, self.allocated_size(layout));
+        result
+    }
+
+    fn allocated_size(&self, layout: Layout) -> usize {
+        self.regions.placement.pages_size(&layout) + 2 * self.regions.placement.guard_size()
+    }
 
     // NOTE Protection acts on an entire page, not a section.
-    fn make_read_only(&self, ptr: *mut u8, layout: Layout) -> io::Result<()> {
-        let size = util::aligned_layout_size(&layout);
-        match unsafe { libc::mprotect(ptr as _, size, PROT_READ) } {
-            -1 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
-        }
+    unsafe fn make_read_only(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        let result = unsafe { self.regions.protect(ptr, &layout, PROT_READ) }
+            .and_then(|_| unsafe { self.regions.check_canary(ptr) });
+
+        stats::MEMFD_SECRET.record_lock(&result);
+        result
     }
 
     // NOTE Protection acts on an entire page, not a section.
-    fn make_writable(&self, ptr: *mut u8, layout: Layout) -> io::Result<()> {
-        let size = util::aligned_layout_size(&layout);
-        match unsafe { libc::mprotect(ptr as _, size, PROT_WRITE | PROT_READ) } {
-            -1 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
-        }
+    unsafe fn make_writable(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        let result = unsafe { self.regions.protect(ptr, &layout, PROT_WRITE | PROT_READ) }
+            .and_then(|_| unsafe { self.regions.check_canary(ptr) });
+
+        stats::MEMFD_SECRET.record_unlock(&result);
+        result
     }
 
-    fn dealloc(&self, ptr: *mut u8, layout: Layout) -> io::Result<()> {
-        self.make_writable(ptr, layout)?;
-        let size = util::aligned_layout_size(&layout);
+    // NOTE Protection acts on an entire page, not a section.
+    unsafe fn make_no_access(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        let result = unsafe { self.regions.protect(ptr, &layout, PROT_NONE) };
+
+        stats::MEMFD_SECRET.record_seal(&result);
+        result
+    }
+
+    unsafe fn dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        unsafe { self.regions.protect(ptr, &layout, PROT_WRITE | PROT_READ) }?;
+        // The memory is released even if the canary does not match
+        let canary_result = unsafe { self.regions.check_canary(ptr) };
+        let (pages, size) = self.regions.pages(ptr, &layout);
+        let (mapping, mapping_size) = self.regions.mapping(ptr, &layout);
 
         Zeroize::zeroize({
-            let bytes_slice = ptr::slice_from_raw_parts_mut(ptr, size);
+            let bytes_slice = ptr::slice_from_raw_parts_mut(pages.cast::<u8>(), size);
             unsafe { &mut *bytes_slice }
         });
 
-        match unsafe { libc::munmap(ptr as _, size) } {
+        // Unmaps the guard pages as well
+        match unsafe { libc::munmap(mapping, mapping_size) } {
             -1 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
+            _ => {
+                budget::release(size);
+                stats::MEMFD_SECRET.record_dealloc(layout.size(), size, mapping_size);
+                canary_result
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use core::{alloc::Layout, ptr, str};
-    use std::io::Write as _;
+    use core::{ptr, sync::atomic::AtomicBool};
 
     use super::*;
+    use crate::alloc::{util::checks, CanaryFailure, ForkPolicy};
 
     #[test]
     fn test_linux_implementation() {
-        let allocator = LinuxSecretAllocator::new();
-        let layout = unsafe { Layout::from_size_align_unchecked(1024, 8) }; // Allocate 1KB with 8-byte alignment
+        checks::implementation(&LinuxSecretAllocator::new());
+    }
 
-        // Assert that allocation was successful
-        let result = allocator.alloc(layout);
-        assert!(result.is_ok());
+    #[test]
+    fn test_linux_guard_pages() {
+        // Forking within the multithreaded test harness is not safe
+        if !crate::util::isolate("alloc::linux::tests::test_linux_guard_pages", &[]) {
+            return;
+        }
 
-        let ptr = unsafe { result.unwrap_unchecked() };
+        // The child probing the guard page must inherit the secret pages
+        let config = Config::new()
+            .guard_pages(true)
+            .fork_policy(ForkPolicy::Inherit);
+        checks::guard_pages(&LinuxSecretAllocator::with_config(&config));
+    }
 
-        // Attempt to write into the allocation
-        let result = {
-            let mut slice_mut = unsafe { &mut *ptr::slice_from_raw_parts_mut(ptr, layout.size()) };
-            write!(slice_mut, "Hello, World!")
-        };
-        assert!(result.is_ok());
+    #[test]
+    fn test_linux_canaries() {
+        static DETECTED: AtomicBool = AtomicBool::new(false);
 
-        // Assert that make_readonly was successful
-        let result = allocator.make_read_only(ptr, layout);
-        assert!(result.is_ok());
+        let config = Config::new()
+            .guard_pages(true)
+            .canaries(true)
+            .canary_failure(CanaryFailure::Callback(|| checks::detect(&DETECTED)));
+        checks::canaries(&LinuxSecretAllocator::with_config(&config), &DETECTED);
+    }
 
-        // Attempt to read from the allocation
-        let result = {
-            let slice_mut = unsafe { &*ptr::slice_from_raw_parts(ptr, layout.size()) };
-            str::from_utf8(slice_mut)
-        };
-        assert!(result.is_ok_and(|s| &s[..13] == "Hello, World!"));
+    #[test]
+    fn test_linux_canaries_fork() {
+        // Forking within the multithreaded test harness is not safe
+        if !crate::util::isolate("alloc::linux::tests::test_linux_canaries_fork", &[]) {
+            return;
+        }
 
-        // Assert that deallocation was successful
-        let result = allocator.dealloc(ptr, layout);
-        assert!(result.is_ok());
+        // The canary is inherited even though the secret pages are not
+        let config = Config::new()
+            .canaries(true)
+            .fork_policy(ForkPolicy::DontFork);
+        checks::canaries_fork(&LinuxSecretAllocator::with_config(&config), false);
+    }
+
+    #[test]
+    fn test_linux_fork_policy() {
+        // Forking within the multithreaded test harness is not safe
+        if !crate::util::isolate("alloc::linux::tests::test_linux_fork_policy", &[]) {
+            return;
+        }
+
+        // Only inherited `memfd_secret` pages are mapped in the child
+        checks::fork_policy(
+            |policy| LinuxSecretAllocator::with_config(&Config::new().fork_policy(policy)),
+            [Some(0xAA), None, None],
+        );
+
+        // And they are shared with it
+        let layout = Layout::new::<[u8; 32]>();
+        let allocator =
+            LinuxSecretAllocator::with_config(&Config::new().fork_policy(ForkPolicy::Inherit));
+        let ptr = allocator.alloc(layout).expect("Failed to allocate");
+        match unsafe { libc::fork() } {
+            0 => unsafe {
+                ptr::write_volatile(ptr.as_ptr(), 0x55);
+                libc::_exit(0);
+            },
+            pid => {
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                assert!(libc::WIFEXITED(status));
+                assert_eq!(unsafe { ptr::read_volatile(ptr.as_ptr()) }, 0x55);
+            }
+        }
+        unsafe { allocator.dealloc(ptr, layout) }.expect("Failed to deallocate");
     }
 }
@@ -1,19 +1,61 @@
-use core::alloc::Layout;
+//! Secret memory allocators.
+//!
+//! This module exposes the [`SecretAllocator`] trait together with its
+//! platform-specific implementations, which can be used directly to obtain raw
+//! secret pages (e.g. for C interop or custom containers). Each of their
+//! allocations spans whole pages, see [`SlabSecretAllocator`] to pack small
+//! secrets together.
+//!
+//! # Example
+//! ```
+//! use core::alloc::Layout;
+//! use secret_mem::alloc::{self, SecretAllocator};
+//!
+//! let secret_alloc = alloc::platform_secret_allocator();
+//! let layout = Layout::new::<[u8; 32]>();
+//!
+//! let ptr = secret_alloc.alloc(layout)?;
+//! unsafe {
+//!     ptr.as_ptr().write_bytes(0x42, layout.size());
+//!     secret_alloc.make_read_only(ptr, layout)?;
+//!     secret_alloc.dealloc(ptr, layout)?;
+//! }
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+use core::{alloc::Layout, ptr::NonNull};
 use std::{io, sync::OnceLock};
 
+mod budget;
+#[cfg(target_family = "unix")]
+mod canary;
+mod config;
+#[cfg(target_family = "unix")]
+mod fork;
 #[cfg(target_os = "linux")]
 mod linux;
+mod slab;
 #[cfg(target_family = "unix")]
 mod unix;
 #[cfg(target_family = "windows")]
 mod windows;
 
+pub use self::budget::{memlock_budget, raise_memlock_limit, MemlockBudget};
+pub use self::config::{
+    AllocatorKind, Backend, CanaryFailure, Config, ForkPolicy, MlockFailure, BACKEND_ENV,
+    FORK_POLICY_ENV, MLOCK_FAILURE_ENV,
+};
 #[cfg(target_os = "linux")]
 pub use self::linux::LinuxSecretAllocator;
+pub use self::slab::SlabSecretAllocator;
 #[cfg(target_family = "unix")]
 pub use self::unix::UnixSecretAllocator;
 #[cfg(target_family = "windows")]
 pub use self::windows::WindowsSecretAllocator;
+pub(crate) use self::{
+    budget::is_quota_exceeded,
+    util::{page_size, take_lock_failure},
+};
 
 /// Trait provides an interface for working with memory that should remain protected
 /// and as invisible as possible. The primary goal is to prevent sensitive data
@@ -24,19 +66,36 @@ pub use self::windows::WindowsSecretAllocator;
 /// Implementations of this trait ensure that memory regions are allocated with
 /// appropriate permissions (e.g., read-only or writable), and that deallocated memory
 /// is securely handled to minimize the risk of sensitive information being leaked.
-pub trait SecretAllocator: Send + Sync {
+///
+/// # Safety
+/// Implementors must return memory blocks that are valid for reads and writes
+/// of `layout.size()` bytes, aligned to `layout.align()` and not aliased by any
+/// other allocation, until they are passed to `dealloc`. Secret containers rely
+/// on this contract to hand out references into the allocated memory.
+pub unsafe trait SecretAllocator: Send + Sync {
     /// Allocates a memory region, according to the specified `layout`, which is
     /// intended to store sensitive data.
     ///
+    /// The returned memory block is writable and zero-initialized.
+    ///
     /// # Parameters:
     /// - `layout`: The layout that defines the size and alignment of the memory block
-    ///   to be allocated.
+    ///   to be allocated. Its size must not be zero.
     ///
     /// # Returns:
     /// - On success, returns a `NonNull<u8>` pointer to the beginning of the allocated
     ///   memory block.
-    /// - On failure, returns an `io::Result` containing the error.
-    fn alloc(&self, layout: Layout) -> io::Result<*mut u8>;
+    /// - On failure, returns an `io::Error` describing the cause.
+    fn alloc(&self, layout: Layout) -> io::Result<NonNull<u8>>;
+
+    /// Returns the number of bytes actually reserved by this allocator for
+    /// an allocation of the given `layout`, which may be larger than
+    /// `layout.size()` (e.g. when rounded up to whole pages).
+    ///
+    /// The default implementation returns `layout.size()`.
+    fn allocated_size(&self, layout: Layout) -> usize {
+        layout.size()
+    }
 
     /// Changes the access permissions of a memory region to make it read-only.
     ///
@@ -49,7 +108,11 @@ pub trait SecretAllocator: Send + Sync {
     ///
     /// # Returns:
     /// On success, returns `Ok(())`. On failure, returns an `io::Error`.
-    fn make_read_only(&self, ptr: *mut u8, layout: Layout) -> io::Result<()>;
+    ///
+    /// # Safety
+    /// `ptr` must have been returned by a call to `alloc` on this allocator with
+    /// the same `layout`, and must not have been deallocated yet.
+    unsafe fn make_read_only(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()>;
 
     /// Changes the access permissions of a memory region to make it writable.
     ///
@@ -63,7 +126,34 @@ pub trait SecretAllocator: Send + Sync {
     ///
     /// # Returns:
     /// On success, returns `Ok(())`. On failure, returns an `io::Error`.
-    fn make_writable(&self, ptr: *mut u8, layout: Layout) -> io::Result<()>;
+    ///
+    /// # Safety
+    /// `ptr` must have been returned by a call to `alloc` on this allocator with
+    /// the same `layout`, and must not have been deallocated yet.
+    unsafe fn make_writable(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()>;
+
+    /// Changes the access permissions of a memory region to make it inaccessible.
+    ///
+    /// This function is typically called on long-lived secrets, so that any
+    /// stray read or write of the memory block faults instead of leaking data.
+    /// Call `make_read_only` or `make_writable` to access the data again.
+    ///
+    /// The default implementation reports the operation as unsupported.
+    ///
+    /// # Parameters:
+    /// - `ptr`: A `NonNull<u8>` pointer to the beginning of the memory block.
+    /// - `layout`: The layout of the memory block, which defines its size and alignment.
+    ///
+    /// # Returns:
+    /// On success, returns `Ok(())`. On failure, returns an `io::Error`.
+    ///
+    /// # Safety
+    /// `ptr` must have been returned by a call to `alloc` on this allocator with
+    /// the same `layout`, and must not have been deallocated yet.
+    unsafe fn make_no_access(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        let _ = (ptr, layout);
+        Err(io::ErrorKind::Unsupported.into())
+    }
 
     /// Deallocates a previously allocated memory region.
     ///
@@ -77,56 +167,326 @@ pub trait SecretAllocator: Send + Sync {
     ///
     /// # Returns:
     /// On success, returns `Ok(())`. On failure, returns an `io::Error`.
-    fn dealloc(&self, ptr: *mut u8, layout: Layout) -> io::Result<()>;
+    ///
+    /// # Safety
+    /// `ptr` must have been returned by a call to `alloc` on this allocator with
+    /// the same `layout`, and must not have been deallocated yet. The memory block
+    /// must not be accessed after this call.
+    unsafe fn dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()>;
+}
+
+unsafe impl<A: SecretAllocator + ?Sized> SecretAllocator for &A {
+    #[inline]
+    fn alloc(&self, layout: Layout) -> io::Result<NonNull<u8>> {
+        (**self).alloc(layout)
+    }
+
+    #[inline]
+    fn allocated_size(&self, layout: Layout) -> usize {
+        (**self).allocated_size(layout)
+    }
+
+    #[inline]
+    unsafe fn make_read_only(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        (**self).make_read_only(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn make_writable(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        (**self).make_writable(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn make_no_access(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        (**self).make_no_access(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        (**self).dealloc(ptr, layout)
+    }
+}
+
+/// The global instance of the platform-specific secret memory allocator.
+static PLATFORM: OnceLock<PlatformInstance> = OnceLock::new();
+
+/// The platform-specific secret memory allocator, along with the
+/// configuration it was built from.
+pub(crate) struct PlatformInstance {
+    pub(crate) kind: AllocatorKind,
+    pub(crate) config: Option<Config>,
+    alloc: Box<dyn SecretAllocator>,
+}
+
+/// Returns the global instance of the platform-specific secret memory
+/// allocator, initializing it if needed.
+pub(crate) fn platform_instance() -> &'static PlatformInstance {
+    PLATFORM.get_or_init(|| {
+        let instance = Config::from_env().and_then(|config| {
+            let (kind, alloc) = config.build()?;
+            config.apply_limits();
+            Ok(PlatformInstance {
+                kind,
+                config: Some(config),
+                alloc,
+            })
+        });
+
+        instance.unwrap_or_else(|error| PlatformInstance {
+            kind: AllocatorKind::Unavailable,
+            config: None,
+            alloc: Box::new(config::Unavailable::new(error)),
+        })
+    })
+}
+
+/// Builds an allocator for the per-process secrets of this crate (e.g. the
+/// canary), which follows the configuration of the platform allocator but
+/// keeps its pages in forked children and stores no canary.
+///
+/// The platform allocator is not initialized by this call, so that
+/// a configuration can still be installed afterwards: until then, the
+/// configuration is read from the environment.
+///
+/// # Errors
+/// Returns an `io::Error` if the configuration cannot be satisfied.
+#[cfg(target_family = "unix")]
+pub(crate) fn process_secret_allocator() -> io::Result<&'static dyn SecretAllocator> {
+    let config = match PLATFORM.get() {
+        Some(instance) => instance.config.clone().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the platform secret memory allocator is unavailable",
+            )
+        })?,
+        None => Config::from_env()?,
+    };
+
+    let config = config.canaries(false).fork_policy(ForkPolicy::Inherit);
+    let (_, alloc) = config.build()?;
+    Ok(Box::leak(alloc))
 }
 
 /// Returns a reference to the global instance of the platform-specific
 /// secret memory allocator.
 ///
+/// The allocator is built from the [`Config`] installed with
+/// [`Config::install`] or, if none was installed before the first call,
+/// from the environment (see [`Config::from_env`]). If that configuration
+/// cannot be satisfied (e.g. `strict-memfd` is requested but `memfd_secret`
+/// is not available), every allocation fails with an `Unsupported` error
+/// instead of silently falling back to a weaker backend.
+///
 /// # Platform-specific behavior
-/// - **Unix**: Checks if `memfd_secret` is supported.
-///   If not available, it falls back to a more general Unix allocator.
+/// - **Linux**: Checks if `memfd_secret` is supported.
+///   If not available, it falls back to a more general Unix allocator
+///   (unless the [`Backend`] forbids it).
+/// - **Unix**: Initializes the general Unix allocator.
 /// - **Windows**: Initializes the general Windows allocator.
+#[inline]
 pub fn platform_secret_allocator() -> &'static dyn SecretAllocator {
-    static INSTANCE: OnceLock<Box<dyn SecretAllocator>> = OnceLock::new();
-    INSTANCE
-        .get_or_init(|| {
-            #[cfg(target_os = "linux")]
-            {
-                match unsafe { libc::syscall(libc::SYS_memfd_secret, 0) } {
-                    -1 => Box::new(UnixSecretAllocator::new()),
-                    fd => {
-                        unsafe { libc::close(fd as libc::c_int) };
-                        Box::new(LinuxSecretAllocator::new())
-                    }
-                }
+    platform_instance().alloc.as_ref()
+}
+
+/// The default secret memory allocator.
+///
+/// This zero-sized type forwards every call to the global instance returned
+/// by [`platform_secret_allocator`], and is the default allocator of the
+/// secret containers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Platform;
+
+unsafe impl SecretAllocator for Platform {
+    #[inline]
+    fn alloc(&self, layout: Layout) -> io::Result<NonNull<u8>> {
+        platform_secret_allocator().alloc(layout)
+    }
+
+    #[inline]
+    fn allocated_size(&self, layout: Layout) -> usize {
+        platform_secret_allocator().allocated_size(layout)
+    }
+
+    #[inline]
+    unsafe fn make_read_only(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        platform_secret_allocator().make_read_only(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn make_writable(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        platform_secret_allocator().make_writable(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn make_no_access(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        platform_secret_allocator().make_no_access(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        platform_secret_allocator().dealloc(ptr, layout)
+    }
+}
+
+mod util {
+    #[cfg(target_family = "unix")]
+    use core::ptr::NonNull;
+    use core::{alloc::Layout, cell::Cell, cmp};
+    use std::{io, sync::OnceLock};
+
+    #[cfg(target_family = "unix")]
+    use super::{
+        canary,
+        config::{CanaryFailure, Config, ForkPolicy},
+    };
+
+    /// The size of the canary stored in front of each user region.
+    pub const CANARY_SIZE: usize = 16;
+
+    /// The placement of user regions within their mappings.
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct Placement {
+        /// Whether the pages are surrounded by inaccessible guard pages.
+        pub guard_pages: bool,
+        /// Whether a canary is stored in front of the user region.
+        pub canary: bool,
+    }
+
+    impl Placement {
+        /// Returns the size of a guard page, or `0` if guard pages are disabled.
+        pub fn guard_size(&self) -> usize {
+            match self.guard_pages {
+                true => self::page_size(),
+                false => 0,
             }
+        }
 
-            #[cfg(all(target_family = "unix", not(target_os = "linux")))]
-            {
-                Box::new(UnixSecretAllocator::new())
+        /// Returns the size of the pages holding a user region of the given
+        /// layout, canary included.
+        pub fn pages_size(&self, layout: &Layout) -> usize {
+            let size = layout.size().wrapping_add(self.canary_padding(layout));
+            let align = cmp::max(layout.align(), self::page_size());
+            size.wrapping_add(align).wrapping_sub(1) & !align.wrapping_sub(1)
+        }
+
+        /// Returns the offset of a user region from the start of its pages.
+        ///
+        /// With guard pages the region is right-aligned, as far as its alignment
+        /// allows, against the trailing guard page so that overflows fault right
+        /// away. Otherwise it starts at the beginning of its pages, right after
+        /// the canary if any.
+        pub fn region_offset(&self, layout: &Layout) -> usize {
+            match self.guard_pages {
+                true => (self.pages_size(layout) - layout.size()) & !(layout.align() - 1),
+                false => self.canary_padding(layout),
             }
+        }
 
-            #[cfg(target_family = "windows")]
-            {
-                Box::new(WindowsSecretAllocator::new())
+        /// Returns the space reserved for the canary in front of a user region,
+        /// which keeps the region aligned.
+        fn canary_padding(&self, layout: &Layout) -> usize {
+            match self.canary {
+                // Both are powers of two
+                true => cmp::max(CANARY_SIZE, layout.align()),
+                false => 0,
             }
-        })
-        .as_ref()
-}
+        }
+    }
 
-mod util {
-    use core::{alloc::Layout, cmp};
-    use std::sync::OnceLock;
+    /// The user regions of the Unix backends: their placement within their
+    /// mappings, and the checks of their canaries.
+    #[cfg(target_family = "unix")]
+    #[derive(Clone, Copy, Debug)]
+    pub struct Regions {
+        pub placement: Placement,
+        pub canary_failure: CanaryFailure,
+        pub fork_policy: ForkPolicy,
+    }
 
-    /// Returns the size of a memory layout aligned to the system's page size.
-    ///
-    /// # Arguments
-    /// * `layout` - A memory layout specifying size and alignment.
-    pub fn aligned_layout_size(layout: &Layout) -> usize {
-        let size = layout.size();
-        let align = cmp::max(layout.align(), self::page_size());
-        size.wrapping_add(align).wrapping_sub(1) & !align.wrapping_sub(1)
+    #[cfg(target_family = "unix")]
+    impl Regions {
+        /// Creates the user regions following the given configuration.
+        pub fn with_config(config: &Config) -> Self {
+            Self {
+                placement: Placement {
+                    guard_pages: config.get_guard_pages(),
+                    canary: config.get_canaries(),
+                },
+                canary_failure: config.get_canary_failure(),
+                fork_policy: config.get_fork_policy(),
+            }
+        }
+
+        /// Returns the start and the size of the pages holding the user region
+        /// which begins at `ptr`.
+        pub fn pages(&self, ptr: NonNull<u8>, layout: &Layout) -> (*mut libc::c_void, usize) {
+            let offset = self.placement.region_offset(layout);
+            let start = unsafe { ptr.as_ptr().sub(offset) };
+            (start as _, self.placement.pages_size(layout))
+        }
+
+        /// Returns the start and the size of the whole mapping, guard pages
+        /// included, holding the user region which begins at `ptr`.
+        pub fn mapping(&self, ptr: NonNull<u8>, layout: &Layout) -> (*mut libc::c_void, usize) {
+            let (pages, size) = self.pages(ptr, layout);
+            let guard_size = self.placement.guard_size();
+            let start = unsafe { pages.cast::<u8>().sub(guard_size) };
+            (start as _, size + 2 * guard_size)
+        }
+
+        /// Changes the protection of the pages holding the user region.
+        ///
+        /// # Safety
+        /// `ptr` must point to a user region of the given `layout`, mapped
+        /// according to these regions.
+        pub unsafe fn protect(
+            &self,
+            ptr: NonNull<u8>,
+            layout: &Layout,
+            prot: libc::c_int,
+        ) -> io::Result<()> {
+            let (pages, size) = self.pages(ptr, layout);
+            match unsafe { libc::mprotect(pages, size, prot) } {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        }
+
+        /// Checks the canary in front of the user region, if enabled.
+        ///
+        /// # Safety
+        /// `ptr` must point to a readable user region, mapped according to
+        /// these regions.
+        pub unsafe fn check_canary(&self, ptr: NonNull<u8>) -> io::Result<()> {
+            match self.placement.canary {
+                true => unsafe { canary::check(ptr, self.canary_failure, self.fork_policy) },
+                false => Ok(()),
+            }
+        }
+    }
+
+    thread_local! {
+        /// The OS error code of the last allocation of this thread which failed
+        /// because its pages could not be locked into RAM.
+        static LOCK_FAILURE: Cell<Option<i32>> = const { Cell::new(None) };
+    }
+
+    /// Clears the lock failure recorded by a previous allocation of this thread.
+    pub fn clear_lock_failure() {
+        LOCK_FAILURE.with(|failure| failure.set(None));
+    }
+
+    /// Records that the allocation failing with `error` could not lock its
+    /// pages into RAM, leaving `error` untouched.
+    pub fn record_lock_failure(error: &io::Error) {
+        LOCK_FAILURE.with(|failure| failure.set(error.raw_os_error()));
+    }
+
+    /// Returns `true` if `error` was recorded with [`record_lock_failure`] by
+    /// the last failed allocation of this thread, clearing the record.
+    pub fn take_lock_failure(error: &io::Error) -> bool {
+        let code = LOCK_FAILURE.with(|failure| failure.take());
+        code.is_some() && code == error.raw_os_error()
     }
 
     /// Returns the system's memory page size in bytes.
@@ -171,6 +531,191 @@ mod util {
         })
     }
 
+    /// Checks shared by the tests of the Unix backends.
+    ///
+    /// The checks which fork must run in a process of their own, see
+    /// [`isolate`](crate::util::isolate).
+    #[cfg(all(test, target_family = "unix"))]
+    pub mod checks {
+        use core::{
+            alloc::Layout,
+            ptr, slice, str,
+            sync::atomic::{AtomicBool, Ordering},
+        };
+        use std::io::{self, Write as _};
+
+        use super::page_size;
+        use crate::alloc::{ForkPolicy, SecretAllocator};
+
+        /// Waits for the forked child `pid`, returning its status.
+        fn wait(pid: libc::pid_t) -> libc::c_int {
+            let mut status = 0;
+            assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+            status
+        }
+
+        /// Checks that a region can be written, read and protected.
+        pub fn implementation<A: SecretAllocator>(allocator: &A) {
+            let layout = Layout::from_size_align(1024, 8).unwrap();
+            let ptr = allocator.alloc(layout).expect("Failed to allocate");
+
+            // Attempt to write into the allocation
+            let result = {
+                let mut slice_mut =
+                    unsafe { &mut *ptr::slice_from_raw_parts_mut(ptr.as_ptr(), layout.size()) };
+                write!(slice_mut, "Hello, World!")
+            };
+            assert!(result.is_ok());
+
+            let result = unsafe { allocator.make_read_only(ptr, layout) };
+            assert!(result.is_ok());
+
+            // Attempt to read from the allocation
+            let result = {
+                let slice = unsafe { &*ptr::slice_from_raw_parts(ptr.as_ptr(), layout.size()) };
+                str::from_utf8(slice)
+            };
+            assert!(result.is_ok_and(|s| &s[..13] == "Hello, World!"));
+
+            let result = unsafe { allocator.make_no_access(ptr, layout) };
+            assert!(result.is_ok());
+
+            let result = unsafe { allocator.dealloc(ptr, layout) };
+            assert!(result.is_ok());
+        }
+
+        /// Checks that a region is right-aligned against a trailing guard
+        /// page, which faults when written. The child must inherit the pages.
+        pub fn guard_pages<A: SecretAllocator>(allocator: &A) {
+            let layout = Layout::from_size_align(1000, 8).unwrap();
+            let ptr = allocator.alloc(layout).expect("Failed to allocate");
+
+            let end = ptr.as_ptr() as usize + layout.size();
+            assert_eq!(end % page_size(), 0);
+
+            unsafe {
+                ptr.as_ptr().write_bytes(0xAA, layout.size());
+                allocator.make_read_only(ptr, layout).unwrap();
+                assert_eq!(*ptr.as_ptr().add(layout.size() - 1), 0xAA);
+            }
+
+            // Writing one byte past the region faults
+            match unsafe { libc::fork() } {
+                0 => unsafe {
+                    allocator.make_writable(ptr, layout).unwrap();
+                    ptr::write_volatile(ptr.as_ptr().add(layout.size()), 0);
+                    libc::_exit(0);
+                },
+                pid => assert!(libc::WIFSIGNALED(wait(pid)), "Overflow should fault"),
+            }
+
+            let result = unsafe { allocator.dealloc(ptr, layout) };
+            assert!(result.is_ok());
+        }
+
+        /// Records a canary mismatch in `detected`, for the
+        /// [`CanaryFailure::Callback`](crate::alloc::CanaryFailure::Callback)
+        /// of the allocator given to [`canaries`].
+        pub fn detect(detected: &AtomicBool) {
+            detected.store(true, Ordering::SeqCst);
+        }
+
+        /// Checks that an underflow is detected by the canary, recorded in
+        /// `detected`, and that the region is released anyway.
+        pub fn canaries<A: SecretAllocator>(allocator: &A, detected: &AtomicBool) {
+            let layout = Layout::from_size_align(1000, 8).unwrap();
+
+            // An intact canary passes every check
+            let ptr = allocator.alloc(layout).expect("Failed to allocate");
+            unsafe {
+                allocator.make_read_only(ptr, layout).unwrap();
+                allocator.make_writable(ptr, layout).unwrap();
+                allocator.dealloc(ptr, layout).unwrap();
+            }
+            assert!(!detected.load(Ordering::SeqCst));
+
+            let ptr = allocator.alloc(layout).expect("Failed to allocate");
+            unsafe {
+                ptr.as_ptr().sub(1).write(!ptr.as_ptr().sub(1).read());
+
+                let error = allocator.make_read_only(ptr, layout).unwrap_err();
+                assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+                assert!(allocator.dealloc(ptr, layout).is_err());
+            }
+            assert!(detected.load(Ordering::SeqCst));
+        }
+
+        /// Checks that a forked child guards its own regions with the
+        /// inherited canary and, if the region of its parent is `inherited`
+        /// (wiped), that it can free it.
+        pub fn canaries_fork<A: SecretAllocator>(allocator: &A, inherited: bool) {
+            let layout = Layout::new::<[u8; 32]>();
+            let ptr = allocator.alloc(layout).expect("Failed to allocate");
+
+            match unsafe { libc::fork() } {
+                0 => unsafe {
+                    let freed = !inherited || allocator.dealloc(ptr, layout).is_ok();
+                    let own = allocator.alloc(layout).unwrap();
+                    let canary = slice::from_raw_parts(own.as_ptr().sub(16), 16);
+                    let intact = canary.iter().any(|&byte| byte != 0)
+                        && allocator.dealloc(own, layout).is_ok();
+                    libc::_exit(!(freed && intact) as libc::c_int);
+                },
+                pid => {
+                    let status = wait(pid);
+                    assert!(libc::WIFEXITED(status), "Child should not fault");
+                    assert_eq!(libc::WEXITSTATUS(status), 0);
+                }
+            }
+
+            let result = unsafe { allocator.dealloc(ptr, layout) };
+            assert!(result.is_ok());
+        }
+
+        /// Checks the first byte seen by a forked child in a region filled
+        /// with `0xAA`, under the `Inherit`, `WipeOnFork` and `DontFork`
+        /// policies in turn, `None` standing for a fault.
+        pub fn fork_policy<A: SecretAllocator>(
+            allocator: impl Fn(ForkPolicy) -> A,
+            expected: [Option<u8>; 3],
+        ) {
+            let layout = Layout::new::<[u8; 32]>();
+            let policies = [
+                ForkPolicy::Inherit,
+                ForkPolicy::WipeOnFork,
+                ForkPolicy::DontFork,
+            ];
+
+            for (policy, expected) in policies.into_iter().zip(expected) {
+                let allocator = allocator(policy);
+                let ptr = allocator.alloc(layout).expect("Failed to allocate");
+                unsafe { ptr.as_ptr().write_bytes(0xAA, layout.size()) };
+
+                match unsafe { libc::fork() } {
+                    0 => unsafe {
+                        let byte = ptr::read_volatile(ptr.as_ptr());
+                        libc::_exit(byte as libc::c_int);
+                    },
+                    pid => {
+                        let status = wait(pid);
+                        match expected {
+                            Some(byte) => {
+                                assert!(libc::WIFEXITED(status), "{policy} child should exit");
+                                assert_eq!(libc::WEXITSTATUS(status), byte as libc::c_int);
+                            }
+                            None => {
+                                assert!(libc::WIFSIGNALED(status), "{policy} child should fault")
+                            }
+                        }
+                    }
+                }
+
+                let result = unsafe { allocator.dealloc(ptr, layout) };
+                assert!(result.is_ok());
+            }
+        }
+    }
+
     #[cfg(test)]
     mod tests {
         use core::alloc::Layout;
@@ -178,20 +723,41 @@ mod util {
         use super::*;
 
         #[test]
-        fn test_aligned_layout_size_with_page_size() {
+        fn test_pages_size_with_page_size() {
             let page_size = page_size();
 
             // Layout with size less than page size, aligned to page size
             let layout = Layout::from_size_align(1000, 8).unwrap();
-            let aligned_size = aligned_layout_size(&layout);
+            let aligned_size = Placement::default().pages_size(&layout);
             assert_eq!(aligned_size, page_size);
 
             // Layout with size larger than a page size
             let layout = Layout::from_size_align(page_size + 1, 8).unwrap();
-            let aligned_size = aligned_layout_size(&layout);
+            let aligned_size = Placement::default().pages_size(&layout);
             assert_eq!(aligned_size, page_size * 2);
         }
 
+        #[cfg(target_family = "unix")]
+        #[test]
+        fn test_lock_failure() {
+            // `ENOMEM` only stands for the locked memory limit when locking
+            let error = io::Error::from_raw_os_error(libc::ENOMEM);
+            record_lock_failure(&error);
+            let error = crate::Error::from_alloc(error);
+            assert!(matches!(error, crate::Error::LockLimitExceeded(_)));
+            assert_eq!(error.io_error().raw_os_error(), Some(libc::ENOMEM));
+
+            // The record is consumed, and cleared by the next allocation
+            let error = crate::Error::from_alloc(io::Error::from_raw_os_error(libc::ENOMEM));
+            assert!(matches!(error, crate::Error::OutOfMemory(_)));
+
+            record_lock_failure(&io::Error::from_raw_os_error(libc::ENOMEM));
+            clear_lock_failure();
+            assert!(!take_lock_failure(&io::Error::from_raw_os_error(
+                libc::ENOMEM
+            )));
+        }
+
         #[test]
         fn test_page_size() {
             let page_size = page_size();
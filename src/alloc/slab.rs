@@ -0,0 +1,536 @@
+use core::{alloc::Layout, ptr::NonNull};
+use std::{
+    collections::BTreeMap,
+    io,
+    sync::{Mutex, MutexGuard},
+};
+
+use zeroize::Zeroize;
+
+use super::{
+    util::{self, CANARY_SIZE},
+    Platform, SecretAllocator,
+};
+
+/// The slot sizes of the slab pages. Larger layouts are forwarded to the
+/// backing allocator.
+const SIZE_CLASSES: [usize; 7] = [16, 32, 64, 128, 256, 512, 1024];
+
+/// A pooled `SecretAllocator` which packs small secrets into size-classed
+/// slots of shared pages, instead of spending a whole page per secret.
+///
+/// The platform-specific allocators map at least a whole page for every
+/// allocation, however small, and guard pages add two more: wrapping one of
+/// them in a `SlabSecretAllocator` lets many small keys share their pages.
+///
+/// Pages are obtained from (and returned to) a backing allocator `A`, which
+/// defaults to the [`Platform`] allocator, so they are locked and protected
+/// the same way. Layouts larger than the biggest slot (1024 bytes) are
+/// forwarded to the backing allocator as they are. Slots are zeroized when
+/// freed, and pages are returned as soon as they are empty.
+///
+/// # Protection
+/// Memory protection acts on whole pages, which are shared by several
+/// secrets. A page is therefore never less restrictive than any of its
+/// slots requires: new slots are only carved out of writable pages, and
+/// making a slot read-only or inaccessible fails with an `Unsupported` error
+/// while it shares its page with other secrets. Only a secret alone on its
+/// page can be locked or sealed, which then keeps the page to itself until
+/// it is unlocked again.
+///
+/// # Example
+/// ```
+/// use secret_mem::{alloc::SlabSecretAllocator, SecretBox};
+///
+/// let slab = SlabSecretAllocator::new();
+/// let key1 = SecretBox::new_in([1u8; 32], &slab);
+/// let key2 = SecretBox::new_in([2u8; 32], &slab);
+///
+/// // Both keys share the same writable page, which cannot be locked for one
+/// // of them only
+/// let key1 = key1.lock().unwrap_err().into_inner();
+///
+/// drop(key2);
+/// let key1 = key1.lock().expect("Failed to lock SecretBox");
+/// assert_eq!(*key1, [1; 32]);
+/// ```
+pub struct SlabSecretAllocator<A: SecretAllocator = Platform> {
+    alloc: A,
+    state: Mutex<SlabState>,
+}
+
+/// The bookkeeping of a [`SlabSecretAllocator`].
+#[derive(Default)]
+struct SlabState {
+    /// The pages, indexed by their address.
+    pages: BTreeMap<usize, SlabPage>,
+    /// The addresses of the writable pages with free slots, for each size
+    /// class.
+    partial: [Vec<usize>; SIZE_CLASSES.len()],
+}
+
+/// A page split into slots of a single size class.
+///
+/// Its used slots all share its protection, which can only change while a
+/// single slot is used.
+struct SlabPage {
+    class: usize,
+    /// The offset of the first slot from the page address, which aligns the
+    /// slots on their size.
+    offset: usize,
+    /// Whether each slot is used.
+    slots: Box<[bool]>,
+    used: usize,
+    protection: Protection,
+}
+
+/// The protection of a page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Protection {
+    Writable,
+    ReadOnly,
+    NoAccess,
+}
+
+impl SlabSecretAllocator {
+    /// Creates a new `SlabSecretAllocator` backed by the [`Platform`] allocator.
+    #[inline]
+    pub fn new() -> Self {
+        Self::new_in(Platform)
+    }
+}
+
+impl<A: SecretAllocator> SlabSecretAllocator<A> {
+    /// Creates a new `SlabSecretAllocator` backed by the given allocator.
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            alloc,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Returns the layout of the pages requested to the backing allocator.
+    ///
+    /// It leaves room for the canary that the backing allocator may store in
+    /// front of it, so that each page spans a single page of memory. Its
+    /// address is then only aligned on the canary size, and the slots are
+    /// aligned within the page instead.
+    fn page_layout() -> Layout {
+        Layout::from_size_align(util::page_size() - CANARY_SIZE, CANARY_SIZE)
+            .expect("Invalid page layout")
+    }
+
+    /// Returns the size class serving `layout`, or `None` if it is too large.
+    fn size_class(layout: &Layout) -> Option<usize> {
+        let size = layout.size().max(layout.align());
+        SIZE_CLASSES.iter().position(|&class| size <= class)
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, SlabState> {
+        // The state is only updated once the backing allocator succeeded,
+        // so it stays consistent even if a panic poisoned the mutex
+        self.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    /// Changes the protection of the slot at `ptr`, which must be alone on
+    /// its page unless the page already has this protection.
+    unsafe fn transition(&self, ptr: NonNull<u8>, to: Protection) -> io::Result<()> {
+        let mut state = self.lock_state();
+        let (base, _) = state.find(ptr);
+        let page = state.pages.get_mut(&base).expect("Unknown slab page");
+
+        if page.protection == to {
+            return Ok(());
+        }
+
+        if page.used > 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the slab page is shared with other secrets",
+            ));
+        }
+
+        unsafe { self.protect(base, to) }?;
+        page.protection = to;
+
+        // New slots are only carved out of writable pages
+        let (class, full) = (page.class, page.used == page.slots.len());
+        match to {
+            Protection::Writable if !full => state.partial[class].push(base),
+            _ => state.partial[class].retain(|&partial| partial != base),
+        }
+
+        Ok(())
+    }
+
+    /// Applies the given protection to the page at `base`.
+    unsafe fn protect(&self, base: usize, protection: Protection) -> io::Result<()> {
+        let ptr = unsafe { NonNull::new_unchecked(base as *mut u8) };
+        let layout = Self::page_layout();
+
+        unsafe {
+            match protection {
+                Protection::Writable => self.alloc.make_writable(ptr, layout),
+                Protection::ReadOnly => self.alloc.make_read_only(ptr, layout),
+                Protection::NoAccess => self.alloc.make_no_access(ptr, layout),
+            }
+        }
+    }
+}
+
+impl Default for SlabSecretAllocator {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<A: SecretAllocator> SecretAllocator for SlabSecretAllocator<A> {
+    fn alloc(&self, layout: Layout) -> io::Result<NonNull<u8>> {
+        let Some(class) = Self::size_class(&layout) else {
+            return self.alloc.alloc(layout);
+        };
+
+        let mut state = self.lock_state();
+        let base = match state.partial[class].last() {
+            Some(&base) => base,
+            None => {
+                let ptr = self.alloc.alloc(Self::page_layout())?;
+                let base = ptr.as_ptr() as usize;
+                state
+                    .pages
+                    .insert(base, SlabPage::new(class, base, Self::page_layout()));
+                state.partial[class].push(base);
+                base
+            }
+        };
+
+        let page = state.pages.get_mut(&base).expect("Unknown slab page");
+        let index = page.slots.iter().position(|&used| !used);
+        let index = index.expect("Partial slab page without free slots");
+
+        page.slots[index] = true;
+        page.used += 1;
+        let ptr = (base + page.offset + index * SIZE_CLASSES[class]) as *mut u8;
+        if page.used == page.slots.len() {
+            state.partial[class].retain(|&partial| partial != base);
+        }
+
+        Ok(unsafe { NonNull::new_unchecked(ptr) })
+    }
+
+    fn allocated_size(&self, layout: Layout) -> usize {
+        match Self::size_class(&layout) {
+            Some(class) => SIZE_CLASSES[class],
+            None => self.alloc.allocated_size(layout),
+        }
+    }
+
+    unsafe fn make_read_only(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        match Self::size_class(&layout) {
+            Some(_) => unsafe { self.transition(ptr, Protection::ReadOnly) },
+            None => unsafe { self.alloc.make_read_only(ptr, layout) },
+        }
+    }
+
+    unsafe fn make_writable(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        match Self::size_class(&layout) {
+            Some(_) => unsafe { self.transition(ptr, Protection::Writable) },
+            None => unsafe { self.alloc.make_writable(ptr, layout) },
+        }
+    }
+
+    unsafe fn make_no_access(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        match Self::size_class(&layout) {
+            Some(_) => unsafe { self.transition(ptr, Protection::NoAccess) },
+            None => unsafe { self.alloc.make_no_access(ptr, layout) },
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        let Some(class) = Self::size_class(&layout) else {
+            return unsafe { self.alloc.dealloc(ptr, layout) };
+        };
+
+        let mut state = self.lock_state();
+        let (base, index) = state.find(ptr);
+        let page = state.pages.get_mut(&base).expect("Unknown slab page");
+
+        // Make the slot writable to zeroize it, which only ever happens to
+        // a page holding no other secret
+        if page.protection != Protection::Writable {
+            unsafe { self.protect(base, Protection::Writable) }?;
+            page.protection = Protection::Writable;
+        }
+
+        Zeroize::zeroize(unsafe {
+            &mut *core::ptr::slice_from_raw_parts_mut(ptr.as_ptr(), SIZE_CLASSES[class])
+        });
+
+        let was_partial = page.used < page.slots.len();
+        page.slots[index] = false;
+        page.used -= 1;
+
+        if page.used == 0 {
+            state.pages.remove(&base);
+            state.partial[class].retain(|&partial| partial != base);
+
+            let ptr = unsafe { NonNull::new_unchecked(base as *mut u8) };
+            return unsafe { self.alloc.dealloc(ptr, Self::page_layout()) };
+        }
+
+        if !was_partial {
+            state.partial[class].push(base);
+        }
+
+        Ok(())
+    }
+}
+
+impl<A: SecretAllocator> Drop for SlabSecretAllocator<A> {
+    fn drop(&mut self) {
+        let state = self
+            .state
+            .get_mut()
+            .unwrap_or_else(|error| error.into_inner());
+        for (base, _) in core::mem::take(&mut state.pages) {
+            let ptr = unsafe { NonNull::new_unchecked(base as *mut u8) };
+            let _ = unsafe { self.alloc.dealloc(ptr, Self::page_layout()) };
+        }
+    }
+}
+
+impl SlabState {
+    /// Returns the address of the page holding `ptr`, and the index of its slot.
+    fn find(&self, ptr: NonNull<u8>) -> (usize, usize) {
+        let addr = ptr.as_ptr() as usize;
+        let (&base, page) = self
+            .pages
+            .range(..=addr)
+            .next_back()
+            .expect("Pointer not allocated by this slab allocator");
+
+        (base, (addr - base - page.offset) / SIZE_CLASSES[page.class])
+    }
+}
+
+impl SlabPage {
+    fn new(class: usize, base: usize, layout: Layout) -> Self {
+        let size = SIZE_CLASSES[class];
+        let offset = (size - base % size) % size;
+        let slots = (layout.size() - offset) / size;
+
+        Self {
+            class,
+            offset,
+            slots: vec![false; slots].into_boxed_slice(),
+            used: 0,
+            protection: Protection::Writable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SecretBox;
+
+    /// Records the calls forwarded to the backing allocator.
+    #[derive(Default)]
+    struct RecordingAllocator(Mutex<Vec<&'static str>>);
+
+    impl RecordingAllocator {
+        fn take(&self) -> Vec<&'static str> {
+            core::mem::take(&mut self.0.lock().unwrap())
+        }
+    }
+
+    unsafe impl SecretAllocator for RecordingAllocator {
+        fn alloc(&self, layout: Layout) -> io::Result<NonNull<u8>> {
+            self.0.lock().unwrap().push("alloc");
+            Platform.alloc(layout)
+        }
+
+        unsafe fn make_read_only(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+            self.0.lock().unwrap().push("make_read_only");
+            Platform.make_read_only(ptr, layout)
+        }
+
+        unsafe fn make_writable(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+            self.0.lock().unwrap().push("make_writable");
+            Platform.make_writable(ptr, layout)
+        }
+
+        unsafe fn make_no_access(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+            self.0.lock().unwrap().push("make_no_access");
+            Platform.make_no_access(ptr, layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+            self.0.lock().unwrap().push("dealloc");
+            Platform.dealloc(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn test_slab_packing() {
+        let slab = SlabSecretAllocator::new_in(RecordingAllocator::default());
+        let layout = Layout::new::<[u8; 32]>();
+
+        let ptr1 = slab.alloc(layout).expect("Failed to allocate slot");
+        let ptr2 = slab.alloc(layout).expect("Failed to allocate slot");
+        assert_eq!(slab.alloc.take(), ["alloc"], "Slots should share a page");
+        assert_eq!(ptr2.as_ptr() as usize - ptr1.as_ptr() as usize, 32);
+        assert_eq!(slab.allocated_size(layout), 32);
+
+        unsafe {
+            ptr1.as_ptr().write_bytes(0xAA, layout.size());
+            slab.dealloc(ptr1, layout)
+                .expect("Failed to deallocate slot");
+
+            // The page is still mapped, as it holds the second slot
+            let slot = core::slice::from_raw_parts(ptr1.as_ptr(), layout.size());
+            assert!(slot.iter().all(|&byte| byte == 0), "Slot was not zeroized");
+
+            let ptr3 = slab.alloc(layout).expect("Failed to allocate slot");
+            assert_eq!(ptr3, ptr1, "Free slots should be reused");
+
+            slab.dealloc(ptr2, layout)
+                .expect("Failed to deallocate slot");
+            slab.dealloc(ptr3, layout)
+                .expect("Failed to deallocate slot");
+        }
+        assert_eq!(
+            slab.alloc.take(),
+            ["dealloc"],
+            "Empty pages should be returned"
+        );
+    }
+
+    #[test]
+    fn test_slab_protection() {
+        let slab = SlabSecretAllocator::new_in(RecordingAllocator::default());
+        let layout = Layout::new::<[u8; 16]>();
+
+        let ptr1 = slab.alloc(layout).expect("Failed to allocate slot");
+        let ptr2 = slab.alloc(layout).expect("Failed to allocate slot");
+        slab.alloc.take();
+
+        unsafe {
+            // A shared page cannot be made more restrictive for one slot only
+            let error = slab.make_read_only(ptr1, layout).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+            let error = slab.make_no_access(ptr2, layout).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+            slab.make_writable(ptr1, layout)
+                .expect("Failed to unprotect slot");
+            assert!(slab.alloc.take().is_empty());
+
+            // A slot alone on its page can
+            slab.dealloc(ptr2, layout)
+                .expect("Failed to deallocate slot");
+            slab.make_read_only(ptr1, layout)
+                .expect("Failed to protect slot");
+            slab.make_no_access(ptr1, layout)
+                .expect("Failed to protect slot");
+            assert_eq!(slab.alloc.take(), ["make_read_only", "make_no_access"]);
+
+            // And keeps it to itself until it is writable again
+            let ptr3 = slab.alloc(layout).expect("Failed to allocate slot");
+            assert_eq!(slab.alloc.take(), ["alloc"]);
+            slab.dealloc(ptr3, layout)
+                .expect("Failed to deallocate slot");
+            assert_eq!(slab.alloc.take(), ["dealloc"]);
+
+            slab.make_writable(ptr1, layout)
+                .expect("Failed to unprotect slot");
+            let ptr4 = slab.alloc(layout).expect("Failed to allocate slot");
+            assert_eq!(slab.alloc.take(), ["make_writable"]);
+            slab.dealloc(ptr4, layout)
+                .expect("Failed to deallocate slot");
+
+            // Freeing a sealed slot must not fault while wiping it
+            slab.make_no_access(ptr1, layout)
+                .expect("Failed to protect slot");
+            slab.dealloc(ptr1, layout)
+                .expect("Failed to deallocate slot");
+            assert_eq!(
+                slab.alloc.take(),
+                ["make_no_access", "make_writable", "dealloc"]
+            );
+        }
+    }
+
+    #[test]
+    fn test_slab_large_layout() {
+        let slab = SlabSecretAllocator::new_in(RecordingAllocator::default());
+        let layout = Layout::from_size_align(2048, 8).unwrap();
+
+        let ptr = slab.alloc(layout).expect("Failed to allocate memory");
+        unsafe {
+            slab.make_read_only(ptr, layout)
+                .expect("Failed to protect memory");
+            slab.dealloc(ptr, layout)
+                .expect("Failed to deallocate memory");
+        }
+
+        assert_eq!(slab.alloc.take(), ["alloc", "make_read_only", "dealloc"]);
+        assert!(slab.state.lock().unwrap().pages.is_empty());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_slab_canaries() {
+        use crate::alloc::{Config, UnixSecretAllocator};
+
+        let config = Config::new().canaries(true).guard_pages(true);
+        let slab = SlabSecretAllocator::new_in(UnixSecretAllocator::with_config(&config));
+
+        // The canary in front of a page does not spill it over a second one
+        let page_layout = SlabSecretAllocator::<Platform>::page_layout();
+        let page_size = util::page_size();
+        assert_eq!(slab.alloc.allocated_size(page_layout), 3 * page_size);
+
+        for size in SIZE_CLASSES {
+            let layout = Layout::from_size_align(size, size).unwrap();
+            let ptrs: Vec<_> = (0..page_size / size - 1)
+                .map(|_| slab.alloc(layout).expect("Failed to allocate slot"))
+                .collect();
+
+            for &ptr in &ptrs {
+                assert_eq!(ptr.as_ptr() as usize % size, 0, "Misaligned slot");
+                unsafe { ptr.as_ptr().write_bytes(0xAA, size) };
+            }
+            assert_eq!(slab.state.lock().unwrap().pages.len(), 1);
+
+            for ptr in ptrs {
+                unsafe { slab.dealloc(ptr, layout) }.expect("Failed to deallocate slot");
+            }
+            assert!(slab.state.lock().unwrap().pages.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_slab_secret_box() {
+        let slab = SlabSecretAllocator::new();
+
+        // Unlocked secrets share their pages
+        let secrets: Vec<_> = (0..8u64).map(|i| SecretBox::new_in(i, &slab)).collect();
+        assert_eq!(slab.state.lock().unwrap().pages.len(), 1);
+
+        // Locked ones do not
+        let locked = SecretBox::new_in(u64::MAX, &slab);
+        let locked = locked.lock().unwrap_err().into_inner();
+        drop(secrets);
+        let locked = locked.lock().expect("Failed to lock SecretBox");
+
+        let mut unlocked = SecretBox::new_in(42u64, &slab);
+        *unlocked += 1;
+        assert_eq!(slab.state.lock().unwrap().pages.len(), 2);
+        assert_eq!((*locked, *unlocked), (u64::MAX, 43));
+
+        drop(locked);
+        drop(unlocked);
+        assert!(slab.state.lock().unwrap().pages.is_empty());
+    }
+}
@@ -1,34 +1,61 @@
-use core::{alloc::Layout, ptr};
+use core::{alloc::Layout, ptr, ptr::NonNull};
 use std::io;
 
-use libc::{MAP_ANON, MAP_FAILED, MAP_PRIVATE, PROT_READ, PROT_WRITE};
+use libc::{MAP_ANON, MAP_FAILED, MAP_PRIVATE, PROT_NONE, PROT_READ, PROT_WRITE};
 use zeroize::Zeroize;
 
-use super::{util, SecretAllocator};
+use super::{
+    budget, canary,
+    config::{self, Config, MlockFailure},
+    fork, util, SecretAllocator,
+};
+use crate::stats;
 
-// FIXME Current implementation wastes memory
 /// Provides an implementation of the `SecretAllocator` trait for Unix-based systems.
 ///
 /// This implementation relies on Unix system calls to manage memory in a way that
 /// limits its visibility to other processes and prevents sensitive data from being
 /// leaked.
-pub struct UnixSecretAllocator(());
+///
+/// The pages are zero-filled or left out in forked children, according to
+/// the [`ForkPolicy`](super::ForkPolicy).
+pub struct UnixSecretAllocator {
+    mlock_failure: MlockFailure,
+    regions: util::Regions,
+}
 
 impl UnixSecretAllocator {
+    /// Creates a new `UnixSecretAllocator`.
     pub fn new() -> Self {
-        Self(())
+        Self::with_config(&Config::new())
+    }
+
+    /// Creates a new `UnixSecretAllocator` following the given configuration.
+    pub fn with_config(config: &Config) -> Self {
+        Self {
+            mlock_failure: config.get_mlock_failure(),
+            regions: util::Regions::with_config(config),
+        }
     }
-}
 
-impl SecretAllocator for UnixSecretAllocator {
-    fn alloc(&self, layout: Layout) -> io::Result<*mut u8> {
-        let size = util::aligned_layout_size(&layout);
+    /// Maps and sets up the pages holding a user region of the given layout.
+    fn map(&self, layout: Layout) -> io::Result<NonNull<u8>> {
+        let size = self.regions.placement.pages_size(&layout);
+        let guard_size = self.regions.placement.guard_size();
+        let mapping_size = size + 2 * guard_size;
+
+        // Guard pages are mapped inaccessible, and only the pages in between
+        // are then made accessible.
+        let prot = match self.regions.placement.guard_pages {
+            true => PROT_NONE,
+            false => PROT_WRITE | PROT_READ,
+        };
 
         let mmap = unsafe {
             libc::mmap(
                 ptr::null_mut(),
-                size,
-                PROT_WRITE | PROT_READ,
+                mapping_size,
+                prot,
                 MAP_PRIVATE | MAP_ANON,
                 -1,
                 0,
@@ -39,115 +66,218 @@ impl SecretAllocator for UnixSecretAllocator {
             return Err(io::Error::last_os_error());
         }
 
-        if unsafe { libc::mlock(mmap, size) } < 0 {
+        let pages = unsafe { mmap.cast::<u8>().add(guard_size) } as *mut libc::c_void;
+
+        if self.regions.placement.guard_pages
+            && unsafe { libc::mprotect(pages, size, PROT_WRITE | PROT_READ) } < 0
+        {
             let last_os_error = io::Error::last_os_error();
-            unsafe { libc::munmap(mmap, size) };
+            unsafe { libc::munmap(mmap, mapping_size) };
             return Err(last_os_error);
         }
 
+        if unsafe { libc::mlock(pages, size) } < 0 {
+            let last_os_error = io::Error::last_os_error();
+
+            if let Err(error) =
+                config::handle_mlock_failure(self.mlock_failure, last_os_error, &stats::MLOCK)
+            {
+                // On Linux `mlock` reports `ENOMEM` when `RLIMIT_MEMLOCK` is exceeded,
+                // which `Error::from_alloc` tells apart from a failed `mmap`.
+                util::record_lock_failure(&error);
+                unsafe { libc::munmap(mmap, mapping_size) };
+                return Err(error);
+            }
+        }
+
         #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
-        let madvise_result = unsafe { libc::madvise(mmap, size, libc::MADV_NOCORE) };
+        let madvise_result = unsafe { libc::madvise(mmap, mapping_size, libc::MADV_NOCORE) };
         #[cfg(not(any(target_os = "freebsd", target_os = "dragonfly")))]
-        let madvise_result = unsafe { libc::madvise(mmap, size, libc::MADV_DONTDUMP) };
+        let madvise_result = unsafe { libc::madvise(mmap, mapping_size, libc::MADV_DONTDUMP) };
 
         if madvise_result < 0 {
             let last_os_error = io::Error::last_os_error();
 
             unsafe {
-                libc::munlock(mmap, size);
-                libc::munmap(mmap, size);
+                libc::munlock(pages, size);
+                libc::munmap(mmap, mapping_size);
             }
 
             return Err(last_os_error);
         }
 
-        Ok(mmap as _)
+        if let Err(error) =
+            unsafe { fork::apply(mmap, mapping_size, self.regions.fork_policy, false) }
+        {
+            unsafe {
+                libc::munlock(pages, size);
+                libc::munmap(mmap, mapping_size);
+            }
+
+            return Err(error);
+        }
+
+        let offset = self.regions.placement.region_offset(&layout);
+        let ptr = unsafe { NonNull::new_unchecked(pages.cast::<u8>().add(offset)) };
+        if self.regions.placement.canary {
+            if let Err(error) = unsafe { canary::write(ptr) } {
+                unsafe {
+                    libc::munlock(pages, size);
+                    libc::munmap(mmap, mapping_size);
+                }
+
+                return Err(error);
+            }
+        }
+
+        Ok(ptr)
+    }
+}
+
+impl Default for UnixSecretAllocator {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl SecretAllocator for UnixSecretAllocator {
+    fn alloc(&self, layout: Layout) -> io::Result<NonNull<u8>> {
+        util::clear_lock_failure();
+        let size = self.regions.placement.pages_size(&layout);
+        budget::reserve(size)?;
+
+        let result = self.map(layout).map_err(|error| {
+            budget::release(size);
+            error
+        });
+
+        stats::MLOCK.record_alloc(&result, layout.size(), size, self.allocated_size(layout));
+        result
+    }
+
+    fn allocated_size(&self, layout: Layout) -> usize {
+        self.regions.placement.pages_size(&layout) + 2 * self.regions.placement.guard_size()
     }
 
     // NOTE Protection acts on an entire page, not a section.
-    fn make_read_only(&self, ptr: *mut u8, layout: Layout) -> io::Result<()> {
-        let size = util::aligned_layout_size(&layout);
-        match unsafe { libc::mprotect(ptr as _, size, PROT_READ) } {
-            -1 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
-        }
+    unsafe fn make_read_only(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        let result = unsafe { self.regions.protect(ptr, &layout, PROT_READ) }
+            .and_then(|_| unsafe { self.regions.check_canary(ptr) });
+
+        stats::MLOCK.record_lock(&result);
+        result
     }
 
     // NOTE Protection acts on an entire page, not a section.
-    fn make_writable(&self, ptr: *mut u8, layout: Layout) -> io::Result<()> {
-        let size = util::aligned_layout_size(&layout);
-        match unsafe { libc::mprotect(ptr as _, size, PROT_WRITE | PROT_READ) } {
-            -1 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
-        }
+    unsafe fn make_writable(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        let result = unsafe { self.regions.protect(ptr, &layout, PROT_WRITE | PROT_READ) }
+            .and_then(|_| unsafe { self.regions.check_canary(ptr) });
+
+        stats::MLOCK.record_unlock(&result);
+        result
     }
 
-    fn dealloc(&self, ptr: *mut u8, layout: Layout) -> io::Result<()> {
-        self.make_writable(ptr, layout)?;
-        let size = util::aligned_layout_size(&layout);
+    // NOTE Protection acts on an entire page, not a section.
+    unsafe fn make_no_access(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        let result = unsafe { self.regions.protect(ptr, &layout, PROT_NONE) };
+
+        stats::MLOCK.record_seal(&result);
+        result
+    }
+
+    unsafe fn dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        unsafe { self.regions.protect(ptr, &layout, PROT_WRITE | PROT_READ) }?;
+        // The memory is released even if the canary does not match
+        let canary_result = unsafe { self.regions.check_canary(ptr) };
+        let (pages, size) = self.regions.pages(ptr, &layout);
+        let (mapping, mapping_size) = self.regions.mapping(ptr, &layout);
 
         Zeroize::zeroize({
-            let bytes_slice = ptr::slice_from_raw_parts_mut(ptr, size);
+            let bytes_slice = ptr::slice_from_raw_parts_mut(pages.cast::<u8>(), size);
             unsafe { &mut *bytes_slice }
         });
 
         // May fail (unchecked)
         unsafe {
             #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
-            libc::madvise(ptr.as_ptr() as *mut _, self.len, libc::MADV_CORE);
+            libc::madvise(mapping, mapping_size, libc::MADV_CORE);
             #[cfg(not(any(target_os = "freebsd", target_os = "dragonfly")))]
-            libc::madvise(ptr as _, size, libc::MADV_DODUMP);
+            libc::madvise(mapping, mapping_size, libc::MADV_DODUMP);
 
-            libc::munlock(ptr as _, size);
+            libc::munlock(pages, size);
         }
 
-        match unsafe { libc::munmap(ptr as _, size) } {
+        // Unmaps the guard pages as well
+        match unsafe { libc::munmap(mapping, mapping_size) } {
             -1 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
+            _ => {
+                budget::release(size);
+                stats::MLOCK.record_dealloc(layout.size(), size, mapping_size);
+                canary_result
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use core::{alloc::Layout, ptr, str};
-    use std::io::Write as _;
+    use core::sync::atomic::AtomicBool;
 
     use super::*;
+    use crate::alloc::{util::checks, CanaryFailure, ForkPolicy};
 
     #[test]
     fn test_unix_implementation() {
-        let allocator = UnixSecretAllocator::new();
-        let layout = unsafe { Layout::from_size_align_unchecked(1024, 8) }; // Allocate 1KB with 8-byte alignment
+        checks::implementation(&UnixSecretAllocator::new());
+    }
 
-        let ptr = {
-            // Assert that allocation was successful
-            let result = allocator.alloc(layout);
-            assert!(result.is_ok());
+    #[test]
+    fn test_unix_guard_pages() {
+        // Forking within the multithreaded test harness is not safe
+        if !crate::util::isolate("alloc::unix::tests::test_unix_guard_pages", &[]) {
+            return;
+        }
 
-            unsafe { result.unwrap_unchecked() }
-        };
+        let allocator = UnixSecretAllocator::with_config(&Config::new().guard_pages(true));
+        checks::guard_pages(&allocator);
+    }
 
-        // Attempt to write into the allocation
-        let result = {
-            let mut slice_mut = unsafe { &mut *ptr::slice_from_raw_parts_mut(ptr, layout.size()) };
-            write!(slice_mut, "Hello, World!")
-        };
-        assert!(result.is_ok());
+    #[test]
+    fn test_unix_canaries() {
+        static DETECTED: AtomicBool = AtomicBool::new(false);
 
-        // Assert that make_readonly was successful
-        let result = allocator.make_read_only(ptr, layout);
-        assert!(result.is_ok());
+        let config = Config::new()
+            .guard_pages(true)
+            .canaries(true)
+            .canary_failure(CanaryFailure::Callback(|| checks::detect(&DETECTED)));
+        checks::canaries(&UnixSecretAllocator::with_config(&config), &DETECTED);
+    }
 
-        // Attempt to read from the allocation
-        let result = {
-            let slice_mut = unsafe { &*ptr::slice_from_raw_parts(ptr, layout.size()) };
-            str::from_utf8(slice_mut)
-        };
-        assert!(result.is_ok_and(|s| &s[..13] == "Hello, World!"));
+    #[test]
+    fn test_unix_canaries_fork() {
+        // Forking within the multithreaded test harness is not safe
+        if !crate::util::isolate("alloc::unix::tests::test_unix_canaries_fork", &[]) {
+            return;
+        }
+
+        // The child frees the region it found wiped
+        let config = Config::new()
+            .canaries(true)
+            .fork_policy(ForkPolicy::WipeOnFork);
+        checks::canaries_fork(&UnixSecretAllocator::with_config(&config), true);
+    }
+
+    #[test]
+    fn test_unix_fork_policy() {
+        // Forking within the multithreaded test harness is not safe
+        if !crate::util::isolate("alloc::unix::tests::test_unix_fork_policy", &[]) {
+            return;
+        }
 
-        // Assert that deallocation was successful
-        let result = allocator.dealloc(ptr, layout);
-        assert!(result.is_ok());
+        checks::fork_policy(
+            |policy| UnixSecretAllocator::with_config(&Config::new().fork_policy(policy)),
+            [Some(0xAA), Some(0), None],
+        );
     }
 }
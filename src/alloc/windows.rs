@@ -1,32 +1,56 @@
-use core::{alloc::Layout, ptr};
+use core::{alloc::Layout, ptr, ptr::NonNull};
 use std::io;
 
 use windows_sys::Win32::System::Memory::{
-    self as windows, MEM_COMMIT, MEM_RELEASE, MEM_RESERVE, PAGE_NOCACHE, PAGE_READONLY,
-    PAGE_READWRITE,
+    self as windows, MEM_COMMIT, MEM_RELEASE, MEM_RESERVE, PAGE_NOACCESS, PAGE_NOCACHE,
+    PAGE_READONLY, PAGE_READWRITE,
 };
 use zeroize::Zeroize;
 
-use super::{util, SecretAllocator};
+use super::{
+    budget,
+    config::{self, Config, MlockFailure},
+    util, SecretAllocator,
+};
+use crate::stats;
 
-// FIXME Current implementation wastes memory
 /// Provides an implementation of the `SecretAllocator` trait for Windows systems.
 ///
 /// This implementation relies on Windows system calls to manage memory in a way that
 /// limits its visibility to other processes and prevents sensitive data from being
 /// leaked.
-pub struct WindowsSecretAllocator(());
+pub struct WindowsSecretAllocator {
+    mlock_failure: MlockFailure,
+}
 
 impl WindowsSecretAllocator {
+    /// Creates a new `WindowsSecretAllocator`.
     pub fn new() -> Self {
-        Self(())
+        Self::with_config(&Config::new())
+    }
+
+    /// Creates a new `WindowsSecretAllocator` following the given configuration.
+    pub fn with_config(config: &Config) -> Self {
+        Self {
+            mlock_failure: config.get_mlock_failure(),
+        }
     }
-}
 
-impl SecretAllocator for WindowsSecretAllocator {
-    fn alloc(&self, layout: Layout) -> io::Result<*mut u8> {
-        let size = util::aligned_layout_size(&layout);
+    /// Changes the protection of the pages holding the user region.
+    fn protect(&self, ptr: NonNull<u8>, layout: &Layout, prot: u32) -> io::Result<()> {
+        let size = util::Placement::default().pages_size(layout);
+        let prot_result = unsafe {
+            windows::VirtualProtect(ptr.as_ptr() as _, size, prot, (&mut 0u32) as *mut _)
+        };
 
+        match prot_result {
+            0 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Allocates and locks `size` bytes of pages.
+    fn map(&self, size: usize) -> io::Result<NonNull<u8>> {
         let virt_alloc = unsafe {
             windows::VirtualAlloc(
                 ptr::null_mut(),
@@ -42,52 +66,87 @@ impl SecretAllocator for WindowsSecretAllocator {
 
         if unsafe { windows::VirtualLock(virt_alloc, size) } == 0 {
             let last_error = io::Error::last_os_error();
-            unsafe { windows::VirtualFree(virt_alloc, 0, MEM_RELEASE) };
-            return Err(last_error);
+
+            if let Err(error) =
+                config::handle_mlock_failure(self.mlock_failure, last_error, &stats::VIRTUAL_LOCK)
+            {
+                util::record_lock_failure(&error);
+                unsafe { windows::VirtualFree(virt_alloc, 0, MEM_RELEASE) };
+                return Err(error);
+            }
         }
 
-        Ok(virt_alloc as _)
+        Ok(unsafe { NonNull::new_unchecked(virt_alloc as _) })
+    }
+}
+
+impl Default for WindowsSecretAllocator {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl SecretAllocator for WindowsSecretAllocator {
+    fn alloc(&self, layout: Layout) -> io::Result<NonNull<u8>> {
+        util::clear_lock_failure();
+        let size = util::Placement::default().pages_size(&layout);
+        budget::reserve(size)?;
+
+        let result = self.map(size).map_err(|error| {
+            budget::release(size);
+            error
+        });
+
+        stats::VIRTUAL_LOCK.record_alloc(&result, layout.size(), size, size);
+        result
+    }
+
+    fn allocated_size(&self, layout: Layout) -> usize {
+        util::Placement::default().pages_size(&layout)
     }
 
     // NOTE Protection acts on an entire page, not a section.
-    fn make_read_only(&self, ptr: *mut u8, layout: Layout) -> io::Result<()> {
-        let size = util::aligned_layout_size(&layout);
-        let prot_result = unsafe {
-            windows::VirtualProtect(ptr as _, size, PAGE_READONLY, (&mut 0u32) as *mut _)
-        };
+    unsafe fn make_read_only(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        let result = self.protect(ptr, &layout, PAGE_READONLY);
 
-        match prot_result {
-            0 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
-        }
+        stats::VIRTUAL_LOCK.record_lock(&result);
+        result
     }
 
     // NOTE Protection acts on an entire page, not a section.
-    fn make_writable(&self, ptr: *mut u8, layout: Layout) -> io::Result<()> {
-        let size = util::aligned_layout_size(&layout);
-        let prot_result = unsafe {
-            windows::VirtualProtect(ptr as _, size, PAGE_READWRITE, (&mut 0u32) as *mut _)
-        };
+    unsafe fn make_writable(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        let result = self.protect(ptr, &layout, PAGE_READWRITE);
 
-        match prot_result {
-            0 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
-        }
+        stats::VIRTUAL_LOCK.record_unlock(&result);
+        result
     }
 
-    fn dealloc(&self, ptr: *mut u8, layout: Layout) -> io::Result<()> {
-        self.make_writable(ptr, layout)?;
-        let size = util::aligned_layout_size(&layout);
+    // NOTE Protection acts on an entire page, not a section.
+    unsafe fn make_no_access(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        let result = self.protect(ptr, &layout, PAGE_NOACCESS);
+
+        stats::VIRTUAL_LOCK.record_seal(&result);
+        result
+    }
+
+    unsafe fn dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+        self.protect(ptr, &layout, PAGE_READWRITE)?;
+        let size = util::Placement::default().pages_size(&layout);
 
         Zeroize::zeroize({
-            let bytes_slice = ptr::slice_from_raw_parts_mut(ptr, size);
+            let bytes_slice = ptr::slice_from_raw_parts_mut(ptr.as_ptr(), size);
             unsafe { &mut *bytes_slice }
         });
 
-        unsafe { windows::VirtualUnlock(ptr as _, size) };
-        match unsafe { windows::VirtualFree(ptr as _, 0, MEM_RELEASE) } {
+        unsafe { windows::VirtualUnlock(ptr.as_ptr() as _, size) };
+        match unsafe { windows::VirtualFree(ptr.as_ptr() as _, 0, MEM_RELEASE) } {
             0 => Err(io::Error::last_os_error()),
-            _ => Ok(()),
+            _ => {
+                budget::release(size);
+                stats::VIRTUAL_LOCK.record_dealloc(layout.size(), size, size);
+                Ok(())
+            }
         }
     }
 }
@@ -121,7 +180,7 @@ mod tests {
         assert!(result.is_ok());
 
         // Assert that make_readonly was successful
-        let result = allocator.make_read_only(ptr, layout);
+        let result = unsafe { allocator.make_read_only(ptr, layout) };
         assert!(result.is_ok());
 
         // Attempt to read from the allocation
@@ -131,8 +190,12 @@ mod tests {
         };
         assert!(result.is_ok_and(|s| &s[..13] == "Hello, World!"));
 
+        // Assert that make_no_access was successful
+        let result = unsafe { allocator.make_no_access(ptr, layout) };
+        assert!(result.is_ok());
+
         // Assert that deallocation was successful
-        let result = allocator.dealloc(ptr, layout);
+        let result = unsafe { allocator.dealloc(ptr, layout) };
         assert!(result.is_ok());
     }
 }
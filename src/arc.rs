@@ -0,0 +1,268 @@
+use core::{
+    fmt,
+    ops::Deref,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{
+    io,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{
+    alloc::{Platform, SecretAllocator},
+    boxed::SecretBox,
+    error::Error,
+    marker::{NoAccess, SecretSafe},
+};
+
+/// A thread-safe reference-counted secure container for sharing secret
+/// values between threads.
+///
+/// The contents are kept inaccessible (see [`NoAccess`]) by default and are
+/// only readable through the guards returned by [`SecretArc::read`]. The number
+/// of live guards is tracked with an atomic counter: the first guard makes the
+/// memory readable, and the memory goes back to no-access only when the last
+/// guard is dropped, so one thread never revokes access from another.
+pub struct SecretArc<T: ?Sized, A: SecretAllocator = Platform> {
+    inner: Arc<SecretArcInner<T, A>>,
+}
+
+struct SecretArcInner<T: ?Sized, A: SecretAllocator> {
+    readers: AtomicUsize,
+    transition: Mutex<()>,
+    secret: SecretBox<T, NoAccess, A>,
+}
+
+impl<T: SecretSafe> SecretArc<T> {
+    /// Creates a new `SecretArc` containing the given value.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretArc::try_new`]
+    /// for a fallible alternative.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self::new_in(value, Platform)
+    }
+
+    /// Attempts to create a new `SecretArc` containing the given value.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails,
+    /// or if the memory cannot be made inaccessible.
+    #[inline]
+    pub fn try_new(value: T) -> Result<Self, Error> {
+        Self::try_new_in(value, Platform)
+    }
+}
+
+impl<T: SecretSafe, A: SecretAllocator> SecretArc<T, A> {
+    /// Creates a new `SecretArc` containing the given value, allocating
+    /// its memory with the given allocator.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretArc::try_new_in`]
+    /// for a fallible alternative.
+    pub fn new_in(value: T, alloc: A) -> Self {
+        Self::try_new_in(value, alloc).expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to create a new `SecretArc` containing the given value,
+    /// allocating its memory with the given allocator.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails,
+    /// or if the memory cannot be made inaccessible.
+    pub fn try_new_in(value: T, alloc: A) -> Result<Self, Error> {
+        let secret = SecretBox::try_new_in(value, alloc)?
+            .seal()
+            .map_err(|e| Error::ProtectionFailed(e.into()))?;
+
+        Ok(Self::from(secret))
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> SecretArc<T, A> {
+    /// Returns a guard which allows reading the contents of the `SecretArc`.
+    ///
+    /// The memory is made readable when the first guard is created, and is
+    /// made inaccessible again when the last guard is dropped.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the memory cannot be made readable.
+    pub fn read(&self) -> io::Result<ReadGuard<'_, T, A>> {
+        let inner = &*self.inner;
+
+        // Fast path, the memory is already readable by another guard
+        let mut readers = inner.readers.load(Ordering::Acquire);
+        while readers > 0 {
+            match inner.readers.compare_exchange_weak(
+                readers,
+                readers + 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Ok(ReadGuard { inner }),
+                Err(current) => readers = current,
+            }
+        }
+
+        // Slow path, the first guard changes the protection
+        let _transition = inner.lock_transition();
+        if inner.readers.load(Ordering::Acquire) == 0 {
+            inner
+                .secret
+                .protect(|alloc, ptr, layout| unsafe { alloc.make_read_only(ptr, layout) })?;
+        }
+
+        inner.readers.fetch_add(1, Ordering::AcqRel);
+        Ok(ReadGuard { inner })
+    }
+
+    /// Returns the number of `SecretArc`s pointing to the same secret.
+    #[inline]
+    pub fn strong_count(this: &Self) -> usize {
+        Arc::strong_count(&this.inner)
+    }
+
+    /// Returns `true` if the two `SecretArc`s point to the same secret.
+    #[inline]
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.inner, &other.inner)
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> SecretArcInner<T, A> {
+    /// Acquires the lock which serializes the protection changes.
+    #[inline]
+    fn lock_transition(&self) -> std::sync::MutexGuard<'_, ()> {
+        self.transition
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> From<SecretBox<T, NoAccess, A>> for SecretArc<T, A> {
+    fn from(secret: SecretBox<T, NoAccess, A>) -> Self {
+        Self {
+            inner: Arc::new(SecretArcInner {
+                readers: AtomicUsize::new(0),
+                transition: Mutex::new(()),
+                secret,
+            }),
+        }
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> Clone for SecretArc<T, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> fmt::Debug for SecretArc<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretArc").finish_non_exhaustive()
+    }
+}
+
+/// A guard which allows reading the contents of a [`SecretArc`],
+/// created by [`SecretArc::read`].
+///
+/// When the last guard of a `SecretArc` is dropped, its memory is made
+/// inaccessible again. If that fails, the memory is left readable until
+/// the next time the last guard is dropped.
+pub struct ReadGuard<'a, T: ?Sized, A: SecretAllocator = Platform> {
+    inner: &'a SecretArcInner<T, A>,
+}
+
+impl<T: ?Sized, A: SecretAllocator> Deref for ReadGuard<'_, T, A> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.inner.secret.as_ptr() }
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> fmt::Debug for ReadGuard<'_, T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadGuard").finish_non_exhaustive()
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> Drop for ReadGuard<'_, T, A> {
+    fn drop(&mut self) {
+        let inner = self.inner;
+
+        // Fast path, other guards keep the memory readable
+        let mut readers = inner.readers.load(Ordering::Acquire);
+        while readers > 1 {
+            match inner.readers.compare_exchange_weak(
+                readers,
+                readers - 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return,
+                Err(current) => readers = current,
+            }
+        }
+
+        // Slow path, the last guard changes the protection
+        let _transition = inner.lock_transition();
+        if inner.readers.fetch_sub(1, Ordering::AcqRel) == 1 {
+            let _ = inner
+                .secret
+                .protect(|alloc, ptr, layout| unsafe { alloc.make_no_access(ptr, layout) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_secretarc_read() {
+        let secret = SecretArc::new([0x42u8; 32]);
+
+        {
+            let guard1 = secret.read().expect("Failed to read SecretArc");
+            let guard2 = secret.read().expect("Failed to read SecretArc");
+            assert_eq!(*guard1, [0x42u8; 32]);
+
+            drop(guard1);
+
+            // The memory is still readable through the remaining guard
+            assert_eq!(*guard2, [0x42u8; 32]);
+        }
+
+        let guard = secret.read().expect("Failed to read SecretArc");
+        assert_eq!(*guard, [0x42u8; 32]);
+    }
+
+    #[test]
+    fn test_secretarc_threads() {
+        let secret = SecretArc::new(0x4242_4242u64);
+
+        let handles = (0..8)
+            .map(|_| {
+                let secret = secret.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        let guard = secret.read().expect("Failed to read SecretArc");
+                        assert_eq!(*guard, 0x4242_4242);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            handle.join().expect("Reader thread panicked");
+        }
+
+        assert_eq!(SecretArc::strong_count(&secret), 1);
+    }
+}
@@ -1,16 +1,31 @@
 use core::{
     alloc::Layout,
-    cmp, fmt, hash,
+    fmt,
     marker::PhantomData,
-    mem::ManuallyDrop,
+    mem::{ManuallyDrop, MaybeUninit},
     ops::{Deref, DerefMut},
-    ptr,
+    ptr::{self, NonNull},
+    str::FromStr,
 };
+use std::{io, process, thread};
+
+#[cfg(not(feature = "no-ord-hash"))]
+use core::{cmp, hash};
+
+#[cfg(not(feature = "no-ord-hash"))]
+use crate::util::keyed_hash::KeyedHasher;
+
+use zeroize::{DefaultIsZeroes, Zeroize, ZeroizeOnDrop};
 
 use crate::{
-    alloc,
-    marker::{Locked, Unlocked},
-    util::Unique,
+    alloc::{Platform, SecretAllocator},
+    error::{Error, LockError, UnlockError},
+    marker::{Locked, NoAccess, Readable, SecretSafe, State, Unlocked},
+    util::{
+        self,
+        ct::{self, ConstantTimeEq},
+        Unique,
+    },
 };
 
 /// A secure container for storing secret values.
@@ -21,136 +36,814 @@ use crate::{
 ///
 /// The underlying memory management is handled using platform-specific
 /// features to protect the memory (e.g., making it read-only, preventing
-/// it from being swapped to disk, etc.).
-pub struct SecretBox<T, L = Unlocked> {
+/// it from being swapped to disk, etc.). The memory is obtained from the
+/// [`SecretAllocator`] `A`, which defaults to the [`Platform`] allocator.
+///
+/// Dynamically sized values are supported through `SecretBox<[T]>` and
+/// `SecretBox<str>`, whose layout is computed from their runtime length.
+pub struct SecretBox<T: ?Sized, L: State = Unlocked, A: SecretAllocator = Platform> {
     pointer: Unique<T>,
+    layout: Layout,
+    alloc: A,
     _marker: PhantomData<L>,
 }
 
-impl<T> SecretBox<T, Unlocked> {
+impl<T: SecretSafe> SecretBox<T, Unlocked> {
     /// Creates a new `SecretBox` containing the given value.
     ///
     /// Allocates secure memory using a platform-specific allocator.
-    /// Panics if the memory allocation fails.
+    /// Panics if the memory allocation fails, see [`SecretBox::try_new`]
+    /// for a fallible alternative.
+    #[inline]
     pub fn new(value: T) -> Self {
-        let pointer = {
-            let secret_alloc = alloc::platform_secret_allocator();
-
-            secret_alloc
-                .alloc(Layout::new::<T>())
-                .map(|p| unsafe {
-                    ptr::write(p as *mut T, value);
-                    Unique::new_unchecked(p as *mut T)
-                })
-                .expect("Unable to allocate secret memory")
-        };
+        Self::new_in(value, Platform)
+    }
+
+    /// Attempts to create a new `SecretBox` containing the given value.
+    ///
+    /// Allocates secure memory using a platform-specific allocator.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_new(value: T) -> Result<Self, Error> {
+        Self::try_new_in(value, Platform)
+    }
+}
+
+impl<T> SecretBox<T, Unlocked> {
+    /// Creates a new `SecretBox` containing the given value, which is not
+    /// required to implement [`SecretSafe`].
+    ///
+    /// **Warning**: only the inline part of the value is stored in secret
+    /// memory. Any heap memory owned by the value (e.g. the bytes of a `String`)
+    /// stays on the global heap, where it is neither locked nor zeroized.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretBox::try_new_shallow`]
+    /// for a fallible alternative.
+    #[inline]
+    pub fn new_shallow(value: T) -> Self {
+        Self::new_shallow_in(value, Platform)
+    }
+
+    /// Attempts to create a new `SecretBox` containing the given value, which
+    /// is not required to implement [`SecretSafe`].
+    ///
+    /// **Warning**: see [`SecretBox::new_shallow`].
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_new_shallow(value: T) -> Result<Self, Error> {
+        Self::try_new_shallow_in(value, Platform)
+    }
+}
+
+impl<T: SecretSafe, A: SecretAllocator> SecretBox<T, Unlocked, A> {
+    /// Creates a new `SecretBox` containing the given value, allocating
+    /// its memory with the given allocator.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretBox::try_new_in`]
+    /// for a fallible alternative.
+    #[inline]
+    pub fn new_in(value: T, alloc: A) -> Self {
+        Self::new_shallow_in(value, alloc)
+    }
+
+    /// Attempts to create a new `SecretBox` containing the given value,
+    /// allocating its memory with the given allocator.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_new_in(value: T, alloc: A) -> Result<Self, Error> {
+        Self::try_new_shallow_in(value, alloc)
+    }
+}
+
+impl<T, A: SecretAllocator> SecretBox<T, Unlocked, A> {
+    /// Creates a new `SecretBox` containing the given value, which is not
+    /// required to implement [`SecretSafe`], allocating its memory with the
+    /// given allocator.
+    ///
+    /// **Warning**: see [`SecretBox::new_shallow`].
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretBox::try_new_shallow_in`] for a fallible alternative.
+    pub fn new_shallow_in(value: T, alloc: A) -> Self {
+        Self::try_new_shallow_in(value, alloc).expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to create a new `SecretBox` containing the given value, which
+    /// is not required to implement [`SecretSafe`], allocating its memory with
+    /// the given allocator.
+    ///
+    /// **Warning**: see [`SecretBox::new_shallow`].
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    pub fn try_new_shallow_in(value: T, alloc: A) -> Result<Self, Error> {
+        let layout = Layout::new::<T>();
+        let pointer = util::allocate(&alloc, layout)?.cast::<T>();
+        unsafe { ptr::write(pointer.as_ptr(), value) };
+
+        Ok(Self {
+            pointer: Unique::from(pointer),
+            layout,
+            alloc,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: SecretSafe> SecretBox<T, Unlocked> {
+    /// Creates a new `SecretBox` whose value is initialized in place by `init`.
+    ///
+    /// The closure receives the zeroed slot inside the secret memory and must
+    /// return a reference to the same slot once it has been initialized, so
+    /// that the value never transits through the caller's stack.
+    ///
+    /// Panics if the memory allocation fails or if `init` returns a reference
+    /// to a different location, see [`SecretBox::try_new_with`] for a fallible
+    /// alternative.
+    #[inline]
+    pub fn new_with<F>(init: F) -> Self
+    where
+        F: FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    {
+        Self::new_with_in(init, Platform)
+    }
+
+    /// Attempts to create a new `SecretBox` whose value is initialized in
+    /// place by `init`.
+    ///
+    /// Panics if `init` returns a reference to a different location than
+    /// the slot it received, see [`SecretBox::new_with`].
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_new_with<F>(init: F) -> Result<Self, Error>
+    where
+        F: FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    {
+        Self::try_new_with_in(init, Platform)
+    }
+
+    /// Creates a new `SecretBox` with uninitialized contents, with the memory
+    /// being filled with `0` bytes.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretBox::try_new_zeroed`]
+    /// for a fallible alternative.
+    #[inline]
+    pub fn new_zeroed() -> SecretBox<MaybeUninit<T>, Unlocked> {
+        Self::new_zeroed_in(Platform)
+    }
+
+    /// Attempts to create a new `SecretBox` with uninitialized contents,
+    /// with the memory being filled with `0` bytes.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_new_zeroed() -> Result<SecretBox<MaybeUninit<T>, Unlocked>, Error> {
+        Self::try_new_zeroed_in(Platform)
+    }
+}
+
+impl<T: Copy + Zeroize + SecretSafe> SecretBox<T, Unlocked> {
+    /// Creates a new `SecretBox` containing a copy of `src`, which is
+    /// zeroized afterwards.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretBox::try_from_mut`]
+    /// for a fallible alternative.
+    #[inline]
+    pub fn from_mut(src: &mut T) -> Self {
+        Self::from_mut_in(src, Platform)
+    }
+
+    /// Attempts to create a new `SecretBox` containing a copy of `src`, which
+    /// is zeroized afterwards.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails,
+    /// in which case `src` is left untouched.
+    #[inline]
+    pub fn try_from_mut(src: &mut T) -> Result<Self, Error> {
+        Self::try_from_mut_in(src, Platform)
+    }
+}
+
+impl<T: SecretSafe, A: SecretAllocator> SecretBox<T, Unlocked, A> {
+    /// Creates a new `SecretBox` whose value is initialized in place by `init`,
+    /// allocating its memory with the given allocator.
+    ///
+    /// Panics if the memory allocation fails or if `init` returns a reference
+    /// to a different location, see [`SecretBox::try_new_with_in`] for a
+    /// fallible alternative.
+    pub fn new_with_in<F>(init: F, alloc: A) -> Self
+    where
+        F: FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    {
+        Self::try_new_with_in(init, alloc).expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to create a new `SecretBox` whose value is initialized in
+    /// place by `init`, allocating its memory with the given allocator.
+    ///
+    /// Panics if `init` returns a reference to a different location than
+    /// the slot it received, see [`SecretBox::new_with`].
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    pub fn try_new_with_in<F>(init: F, alloc: A) -> Result<Self, Error>
+    where
+        F: FnOnce(&mut MaybeUninit<T>) -> &mut T,
+    {
+        let mut secret = Self::try_new_zeroed_in(alloc)?;
+
+        let slot = secret.as_ptr().cast::<T>();
+        let value: *mut T = init(&mut secret);
+        assert!(
+            ptr::eq(value, slot),
+            "The initializer must return the slot it was given"
+        );
+
+        // SAFETY: the initializer returned a reference to the slot.
+        Ok(unsafe { secret.assume_init() })
+    }
+
+    /// Creates a new `SecretBox` with uninitialized contents, with the memory
+    /// being filled with `0` bytes, allocating its memory with the given
+    /// allocator.
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretBox::try_new_zeroed_in`] for a fallible alternative.
+    pub fn new_zeroed_in(alloc: A) -> SecretBox<MaybeUninit<T>, Unlocked, A> {
+        Self::try_new_zeroed_in(alloc).expect("Unable to allocate secret memory")
+    }
 
-        Self {
-            pointer,
+    /// Attempts to create a new `SecretBox` with uninitialized contents,
+    /// with the memory being filled with `0` bytes, allocating its memory
+    /// with the given allocator.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    pub fn try_new_zeroed_in(alloc: A) -> Result<SecretBox<MaybeUninit<T>, Unlocked, A>, Error> {
+        let layout = Layout::new::<T>();
+        let pointer = util::allocate(&alloc, layout)?.cast::<MaybeUninit<T>>();
+        unsafe { ptr::write_bytes(pointer.as_ptr(), 0, 1) };
+
+        Ok(SecretBox {
+            pointer: Unique::from(pointer),
+            layout,
+            alloc,
             _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: Copy + Zeroize + SecretSafe, A: SecretAllocator> SecretBox<T, Unlocked, A> {
+    /// Creates a new `SecretBox` containing a copy of `src`, which is
+    /// zeroized afterwards, allocating its memory with the given allocator.
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretBox::try_from_mut_in`] for a fallible alternative.
+    pub fn from_mut_in(src: &mut T, alloc: A) -> Self {
+        Self::try_from_mut_in(src, alloc).expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to create a new `SecretBox` containing a copy of `src`, which
+    /// is zeroized afterwards, allocating its memory with the given allocator.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails,
+    /// in which case `src` is left untouched.
+    pub fn try_from_mut_in(src: &mut T, alloc: A) -> Result<Self, Error> {
+        let secret = Self::try_new_zeroed_in(alloc)?;
+        unsafe { ptr::copy_nonoverlapping(src, secret.as_ptr().cast::<T>(), 1) };
+        src.zeroize();
+
+        // SAFETY: the value has been copied into the slot.
+        Ok(unsafe { secret.assume_init() })
+    }
+}
+
+impl<T, L: State, A: SecretAllocator> SecretBox<MaybeUninit<T>, L, A> {
+    /// Converts to `SecretBox<T, L, A>`.
+    ///
+    /// # Safety
+    /// As with [`MaybeUninit::assume_init`], it is up to the caller to
+    /// guarantee that the value really is in an initialized state.
+    /// Calling this when the content is not yet fully initialized causes
+    /// immediate undefined behavior.
+    pub unsafe fn assume_init(self) -> SecretBox<T, L, A> {
+        let this = ManuallyDrop::new(self);
+
+        // `MaybeUninit<T>` is guaranteed to have the same layout as `T`.
+        SecretBox {
+            pointer: Unique::from(this.pointer.as_non_null_ptr().cast::<T>()),
+            layout: this.layout,
+            alloc: unsafe { ptr::read(&this.alloc) },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Copy + SecretSafe> SecretBox<[T], Unlocked> {
+    /// Creates a new `SecretBox` containing a copy of the given slice.
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretBox::try_from_slice_copy`] for a fallible alternative.
+    #[inline]
+    pub fn from_slice_copy(slice: &[T]) -> Self {
+        Self::from_slice_copy_in(slice, Platform)
+    }
+
+    /// Attempts to create a new `SecretBox` containing a copy of the given slice.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_from_slice_copy(slice: &[T]) -> Result<Self, Error> {
+        Self::try_from_slice_copy_in(slice, Platform)
+    }
+}
+
+impl<T: Copy + SecretSafe, A: SecretAllocator> SecretBox<[T], Unlocked, A> {
+    /// Creates a new `SecretBox` containing a copy of the given slice,
+    /// allocating its memory with the given allocator.
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretBox::try_from_slice_copy_in`] for a fallible alternative.
+    pub fn from_slice_copy_in(slice: &[T], alloc: A) -> Self {
+        Self::try_from_slice_copy_in(slice, alloc).expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to create a new `SecretBox` containing a copy of the given slice,
+    /// allocating its memory with the given allocator.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    pub fn try_from_slice_copy_in(slice: &[T], alloc: A) -> Result<Self, Error> {
+        let this = Self::try_new_uninit_slice_in(slice.len(), alloc)?;
+        unsafe {
+            let pointer = this.pointer.as_ptr() as *mut T;
+            ptr::copy_nonoverlapping(slice.as_ptr(), pointer, slice.len());
         }
+
+        Ok(this)
+    }
+}
+
+impl<T: DefaultIsZeroes + SecretSafe> SecretBox<[T], Unlocked> {
+    /// Creates a new `SecretBox` containing a zeroed slice of the given length.
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretBox::try_new_zeroed_slice`] for a fallible alternative.
+    #[inline]
+    pub fn new_zeroed_slice(len: usize) -> Self {
+        Self::new_zeroed_slice_in(len, Platform)
+    }
+
+    /// Attempts to create a new `SecretBox` containing a zeroed slice of the given length.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_new_zeroed_slice(len: usize) -> Result<Self, Error> {
+        Self::try_new_zeroed_slice_in(len, Platform)
+    }
+}
+
+impl<T: DefaultIsZeroes + SecretSafe, A: SecretAllocator> SecretBox<[T], Unlocked, A> {
+    /// Creates a new `SecretBox` containing a zeroed slice of the given length,
+    /// allocating its memory with the given allocator.
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretBox::try_new_zeroed_slice_in`] for a fallible alternative.
+    pub fn new_zeroed_slice_in(len: usize, alloc: A) -> Self {
+        Self::try_new_zeroed_slice_in(len, alloc).expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to create a new `SecretBox` containing a zeroed slice of the given
+    /// length, allocating its memory with the given allocator.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    pub fn try_new_zeroed_slice_in(len: usize, alloc: A) -> Result<Self, Error> {
+        let this = Self::try_new_uninit_slice_in(len, alloc)?;
+        unsafe { ptr::write_bytes(this.pointer.as_ptr() as *mut T, 0, len) };
+
+        Ok(this)
+    }
+}
+
+impl<T, A: SecretAllocator> SecretBox<[T], Unlocked, A> {
+    /// Allocates a slice of `len` elements, leaving its contents to be
+    /// initialized by the caller.
+    fn try_new_uninit_slice_in(len: usize, alloc: A) -> Result<Self, Error> {
+        let layout = Layout::array::<T>(len)
+            .map_err(|_| Error::OutOfMemory(io::ErrorKind::OutOfMemory.into()))?;
+        let pointer = util::allocate(&alloc, layout)?.cast::<T>();
+
+        Ok(Self {
+            pointer: Unique::from(NonNull::slice_from_raw_parts(pointer, len)),
+            layout,
+            alloc,
+            _marker: PhantomData,
+        })
     }
+}
+
+impl SecretBox<str, Unlocked> {
+    /// Attempts to create a new `SecretBox` containing a copy of the given
+    /// string slice.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_from_str(s: &str) -> Result<Self, Error> {
+        Self::try_from_str_in(s, Platform)
+    }
+}
 
+impl<A: SecretAllocator> SecretBox<str, Unlocked, A> {
+    /// Creates a new `SecretBox` containing a copy of the given string slice,
+    /// allocating its memory with the given allocator.
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretBox::try_from_str_in`] for a fallible alternative.
+    pub fn from_str_in(s: &str, alloc: A) -> Self {
+        Self::try_from_str_in(s, alloc).expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to create a new `SecretBox` containing a copy of the given
+    /// string slice, allocating its memory with the given allocator.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    pub fn try_from_str_in(s: &str, alloc: A) -> Result<Self, Error> {
+        let bytes = SecretBox::<[u8], Unlocked, A>::try_from_slice_copy_in(s.as_bytes(), alloc)?;
+        let bytes = ManuallyDrop::new(bytes);
+
+        // The bytes have been copied from a valid `str`
+        Ok(Self {
+            pointer: unsafe { Unique::new_unchecked(bytes.pointer.as_ptr() as *mut str) },
+            layout: bytes.layout,
+            alloc: unsafe { ptr::read(&bytes.alloc) },
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl From<&str> for SecretBox<str, Unlocked> {
+    /// Creates a new `SecretBox` containing a copy of the given string slice.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretBox::try_from_str`]
+    /// for a fallible alternative.
+    #[inline]
+    fn from(s: &str) -> Self {
+        Self::from_str_in(s, Platform)
+    }
+}
+
+impl FromStr for SecretBox<str, Unlocked> {
+    type Err = Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from_str(s)
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> SecretBox<T, Unlocked, A> {
     /// Locks the `SecretBox`, making its contents read-only.
     ///
     /// If successful, returns a `SecretBox` in the `Locked` state,
     /// preventing further modifications.
-    /// If it fails, it returns the original `SecretBox`.
     ///
     /// # Errors
-    /// Returns an error if the memory cannot be made read-only.
-    pub fn lock(self) -> Result<SecretBox<T, Locked>, Self> {
-        let secret_alloc = alloc::platform_secret_allocator();
+    /// Returns a [`LockError`] carrying the original `SecretBox` and the
+    /// cause if the memory cannot be made read-only.
+    pub fn lock(self) -> Result<SecretBox<T, Locked, A>, LockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_read_only(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(LockError::new(self, e)),
+        }
+    }
 
-        let pointer = self.pointer.as_ptr() as _;
-        let layout = Layout::new::<T>();
+    /// Seals the `SecretBox`, making its contents inaccessible.
+    ///
+    /// If successful, returns a `SecretBox` in the `NoAccess` state,
+    /// where any access to the contents faults.
+    ///
+    /// # Errors
+    /// Returns a [`LockError`] carrying the original `SecretBox` and the
+    /// cause if the memory cannot be made inaccessible.
+    pub fn seal(self) -> Result<SecretBox<T, NoAccess, A>, LockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_no_access(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(LockError::new(self, e)),
+        }
+    }
+}
 
-        match secret_alloc.make_read_only(pointer, layout) {
-            Ok(_) => {
-                let this = ManuallyDrop::new(self);
+impl<T: ?Sized + Zeroize, A: SecretAllocator> SecretBox<T, Unlocked, A> {
+    /// Clears the contents of the `SecretBox` in place, without deallocating
+    /// its memory.
+    #[inline]
+    pub fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
 
-                Ok(SecretBox::<T, Locked> {
-                    pointer: this.pointer,
-                    _marker: PhantomData,
-                })
-            }
-            Err(_) => Err(self),
+impl<T: ?Sized, A: SecretAllocator> SecretBox<T, Locked, A> {
+    /// Unlocks the `SecretBox`, allowing modifications to its contents.
+    ///
+    /// If successful, returns a `SecretBox` in the `Unlocked` state.
+    ///
+    /// # Errors
+    /// Returns an [`UnlockError`] carrying the original `SecretBox` and the
+    /// cause if the memory cannot be made writable.
+    pub fn unlock(self) -> Result<SecretBox<T, Unlocked, A>, UnlockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_writable(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(UnlockError::new(self, e)),
+        }
+    }
+
+    /// Seals the `SecretBox`, making its contents inaccessible.
+    ///
+    /// If successful, returns a `SecretBox` in the `NoAccess` state,
+    /// where any access to the contents faults.
+    ///
+    /// # Errors
+    /// Returns a [`LockError`] carrying the original `SecretBox` and the
+    /// cause if the memory cannot be made inaccessible.
+    pub fn seal(self) -> Result<SecretBox<T, NoAccess, A>, LockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_no_access(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(LockError::new(self, e)),
         }
     }
 }
 
-impl<T> SecretBox<T, Locked> {
+impl<T: ?Sized, A: SecretAllocator> SecretBox<T, Locked, A> {
+    /// Temporarily unlocks the `SecretBox`, returning a guard which allows
+    /// modifications to its contents.
+    ///
+    /// The read-only protection is restored when the guard is dropped,
+    /// including during unwinding. Use [`WriteGuard::finish`] to handle
+    /// any failure while restoring the protection.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the memory cannot be made writable.
+    pub fn write(&mut self) -> io::Result<WriteGuard<'_, T, A>> {
+        self.protect(|alloc, ptr, layout| unsafe { alloc.make_writable(ptr, layout) })?;
+        Ok(WriteGuard { secret: self })
+    }
+
+    /// Temporarily unlocks the `SecretBox` and calls `f` with a mutable
+    /// reference to its contents, restoring the read-only protection
+    /// afterwards (even if `f` panics).
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the memory cannot be made writable, or
+    /// if the read-only protection cannot be restored.
+    pub fn with_mut<R, F>(&mut self, f: F) -> io::Result<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        let mut guard = self.write()?;
+        let result = f(&mut guard);
+        guard.finish().map(|_| result)
+    }
+}
+
+impl<T: ?Sized + Zeroize, A: SecretAllocator> SecretBox<T, Locked, A> {
+    /// Clears the contents of the `SecretBox` in place, without deallocating
+    /// its memory, restoring the read-only protection afterwards.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the memory cannot be made writable, or
+    /// if the read-only protection cannot be restored.
+    #[inline]
+    pub fn wipe(&mut self) -> io::Result<()> {
+        self.with_mut(Zeroize::zeroize)
+    }
+}
+
+impl<T: ?Sized + Zeroize, A: SecretAllocator> SecretBox<T, NoAccess, A> {
+    /// Clears the contents of the `SecretBox` in place, without deallocating
+    /// its memory, making it inaccessible again afterwards.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the memory cannot be made writable, or
+    /// if it cannot be made inaccessible again. In the latter case the
+    /// memory is left writable.
+    pub fn wipe(&mut self) -> io::Result<()> {
+        self.protect(|alloc, ptr, layout| unsafe { alloc.make_writable(ptr, layout) })?;
+        unsafe { (*self.as_ptr()).zeroize() };
+        self.protect(|alloc, ptr, layout| unsafe { alloc.make_no_access(ptr, layout) })
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> SecretBox<T, NoAccess, A> {
+    /// Unseals the `SecretBox`, making its contents readable again.
+    ///
+    /// If successful, returns a `SecretBox` in the `Locked` state.
+    ///
+    /// # Errors
+    /// Returns an [`UnlockError`] carrying the original `SecretBox` and the
+    /// cause if the memory cannot be made read-only.
+    pub fn unseal(self) -> Result<SecretBox<T, Locked, A>, UnlockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_read_only(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(UnlockError::new(self, e)),
+        }
+    }
+
     /// Unlocks the `SecretBox`, allowing modifications to its contents.
     ///
     /// If successful, returns a `SecretBox` in the `Unlocked` state.
-    /// If it fails, it returns the original `SecretBox`.
     ///
     /// # Errors
-    /// Returns an error if the memory cannot be made writable.
-    pub fn unlock(self) -> Result<SecretBox<T, Unlocked>, Self> {
-        let secret_alloc = alloc::platform_secret_allocator();
+    /// Returns an [`UnlockError`] carrying the original `SecretBox` and the
+    /// cause if the memory cannot be made writable.
+    pub fn unlock(self) -> Result<SecretBox<T, Unlocked, A>, UnlockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_writable(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(UnlockError::new(self, e)),
+        }
+    }
+}
 
-        let pointer = self.pointer.as_ptr() as _;
-        let layout = Layout::new::<T>();
+impl<T: ?Sized, L: State, A: SecretAllocator> SecretBox<T, L, A> {
+    /// Returns a reference to the underlying allocator.
+    ///
+    /// Note: this is an associated function, which means that you have
+    /// to call it as `SecretBox::allocator(&b)` instead of `b.allocator()`.
+    #[inline]
+    pub fn allocator(this: &Self) -> &A {
+        &this.alloc
+    }
 
-        match secret_alloc.make_writable(pointer, layout) {
-            Ok(_) => {
-                let this = ManuallyDrop::new(self);
+    /// Returns the number of bytes reserved for the secret memory region,
+    /// including the overhead of the allocator (e.g. the rounding up to
+    /// whole pages), or `0` for zero-sized values.
+    ///
+    /// Note: this is an associated function, which means that you have
+    /// to call it as `SecretBox::allocated_size(&b)` instead of `b.allocated_size()`.
+    #[inline]
+    pub fn allocated_size(this: &Self) -> usize {
+        match this.layout.size() {
+            0 => 0,
+            _ => this.alloc.allocated_size(this.layout),
+        }
+    }
 
-                Ok(SecretBox::<T, Unlocked> {
-                    pointer: this.pointer,
-                    _marker: PhantomData,
-                })
-            }
-            Err(_) => Err(self),
+    /// Acquires the underlying `*mut` pointer, regardless of the state.
+    #[inline]
+    pub(crate) fn as_ptr(&self) -> *mut T {
+        self.pointer.as_ptr()
+    }
+
+    /// Applies a protection change to the allocated memory region, if any.
+    pub(crate) fn protect<F>(&self, f: F) -> io::Result<()>
+    where
+        F: FnOnce(&A, NonNull<u8>, Layout) -> io::Result<()>,
+    {
+        match self.layout.size() {
+            0 => Ok(()),
+            _ => f(
+                &self.alloc,
+                self.pointer.as_non_null_ptr().cast(),
+                self.layout,
+            ),
+        }
+    }
+
+    /// Moves the pointer and the allocator into a `SecretBox` with
+    /// a different state marker, without running `Drop`.
+    fn into_state<M: State>(self) -> SecretBox<T, M, A> {
+        let this = ManuallyDrop::new(self);
+
+        SecretBox {
+            pointer: this.pointer,
+            layout: this.layout,
+            alloc: unsafe { ptr::read(&this.alloc) },
+            _marker: PhantomData,
         }
     }
 }
 
-impl<T: PartialEq, L> PartialEq for SecretBox<T, L> {
+impl<T: ?Sized + ConstantTimeEq, L: Readable, A: SecretAllocator> SecretBox<T, L, A> {
+    /// Compares the contents with `other` in constant time.
+    ///
+    /// The running time only depends on the length of the values.
+    #[inline]
+    pub fn ct_eq(&self, other: &T) -> bool {
+        ConstantTimeEq::ct_eq(&**self, other)
+    }
+}
+
+impl<T: ?Sized + AsRef<[u8]>, L: Readable, A: SecretAllocator> SecretBox<T, L, A> {
+    /// Checks whether the contents only consist of zero bytes, in
+    /// constant time.
+    #[inline]
+    pub fn ct_is_zero(&self) -> bool {
+        ct::ct_is_zero((**self).as_ref())
+    }
+}
+
+impl<T: ?Sized + AsMut<[u8]>, A: SecretAllocator> SecretBox<T, Unlocked, A> {
+    /// Replaces the contents with the bytes of `other` if `choice` is `true`,
+    /// in constant time with respect to both `choice` and the contents.
+    ///
+    /// # Panics
+    /// Panics if `other` does not have the same length as the contents.
+    #[inline]
+    pub fn ct_select(&mut self, other: &[u8], choice: bool) {
+        ct::ct_select((**self).as_mut(), other, choice);
+    }
+}
+
+#[cfg(not(feature = "ct-eq"))]
+impl<T: ?Sized + PartialEq, L: Readable, A: SecretAllocator> PartialEq for SecretBox<T, L, A> {
     #[inline]
     fn eq(&self, other: &Self) -> bool {
         PartialEq::eq(&**self, &**other)
     }
 }
 
-impl<T: Eq, L> Eq for SecretBox<T, L> {}
+#[cfg(not(feature = "ct-eq"))]
+impl<T: ?Sized + Eq, L: Readable, A: SecretAllocator> Eq for SecretBox<T, L, A> {}
+
+#[cfg(feature = "ct-eq")]
+impl<T: ?Sized + ConstantTimeEq, L: Readable, A: SecretAllocator> PartialEq for SecretBox<T, L, A> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other)
+    }
+}
+
+#[cfg(feature = "ct-eq")]
+impl<T: ?Sized + ConstantTimeEq, L: Readable, A: SecretAllocator> Eq for SecretBox<T, L, A> {}
 
-impl<T: PartialOrd, L> PartialOrd for SecretBox<T, L> {
+#[cfg(not(feature = "no-ord-hash"))]
+impl<T: ?Sized + PartialOrd, L: Readable, A: SecretAllocator> PartialOrd for SecretBox<T, L, A>
+where
+    Self: PartialEq,
+{
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
         PartialOrd::partial_cmp(&**self, &**other)
     }
 }
 
-impl<T: Ord, L> Ord for SecretBox<T, L> {
+#[cfg(not(feature = "no-ord-hash"))]
+impl<T: ?Sized + Ord, L: Readable, A: SecretAllocator> Ord for SecretBox<T, L, A>
+where
+    Self: Eq,
+{
     #[inline]
     fn cmp(&self, other: &Self) -> cmp::Ordering {
         Ord::cmp(&**self, &**other)
     }
 }
 
-impl<T: hash::Hash, L> hash::Hash for SecretBox<T, L> {
+/// The contents are hashed with SipHash-2-4 keyed by a per-process random
+/// key, which is held in secret memory. Only the resulting digest is fed to
+/// `state`, so the hasher in use never observes the secret itself.
+///
+/// If secret memory cannot be allocated when the key is first needed, the
+/// key is kept in ordinary memory instead: hashing never fails, and still
+/// hides the secret from the hasher in use.
+#[cfg(not(feature = "no-ord-hash"))]
+impl<T: ?Sized + hash::Hash, L: Readable, A: SecretAllocator> hash::Hash for SecretBox<T, L, A> {
     fn hash<H: hash::Hasher>(&self, state: &mut H) {
-        (**self).hash(state);
+        let mut hasher = KeyedHasher::new();
+        (**self).hash(&mut hasher);
+        state.write_u64(hash::Hasher::finish(&hasher));
     }
 }
 
-impl<T, L> AsRef<T> for SecretBox<T, L> {
+impl<T: ?Sized, L: Readable, A: SecretAllocator> AsRef<T> for SecretBox<T, L, A> {
     #[inline]
     fn as_ref(&self) -> &T {
         self
     }
 }
 
-impl<T> AsMut<T> for SecretBox<T, Unlocked> {
+impl<T: ?Sized, A: SecretAllocator> AsMut<T> for SecretBox<T, Unlocked, A> {
     #[inline]
     fn as_mut(&mut self) -> &mut T {
         self
     }
 }
 
-impl<T, L> Deref for SecretBox<T, L> {
+impl<T: ?Sized, L: Readable, A: SecretAllocator> Deref for SecretBox<T, L, A> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
@@ -158,42 +851,143 @@ impl<T, L> Deref for SecretBox<T, L> {
     }
 }
 
-impl<T> DerefMut for SecretBox<T, Unlocked> {
+impl<T: ?Sized, A: SecretAllocator> DerefMut for SecretBox<T, Unlocked, A> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         unsafe { &mut *self.pointer.as_ptr() }
     }
 }
 
-impl<T, L> fmt::Debug for SecretBox<T, L> {
+impl<T: ?Sized, L: State, A: SecretAllocator> fmt::Debug for SecretBox<T, L, A> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("SecretBox").finish_non_exhaustive()
     }
 }
 
-impl<T: Default> Default for SecretBox<T, Unlocked> {
+impl<T: Default + SecretSafe> SecretBox<T, Unlocked> {
+    /// Attempts to create a new `SecretBox` containing the default value of `T`.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_default() -> Result<Self, Error> {
+        SecretBox::try_new(T::default())
+    }
+}
+
+impl<T: Default + SecretSafe> Default for SecretBox<T, Unlocked> {
     #[inline]
     fn default() -> Self {
         SecretBox::new(T::default())
     }
 }
 
-impl<T, L> Drop for SecretBox<T, L> {
+impl<T: ?Sized, L: State, A: SecretAllocator> Drop for SecretBox<T, L, A> {
     fn drop(&mut self) {
-        let secret_alloc = alloc::platform_secret_allocator();
-        let pointer = self.pointer.as_ptr();
+        // The memory of a parent process may be missing from a forked child,
+        // where wiping it would fault
+        if util::is_left_out(self.pointer.as_non_null_ptr().cast(), self.layout.size()) {
+            return;
+        }
+
+        // Make the memory writable unless it already is, since the destructor
+        // of the value may write to it. If this fails the value is leaked,
+        // as dropping it in place could fault.
+        let writable = match L::WRITABLE {
+            true => Ok(()),
+            false => self.protect(|alloc, ptr, layout| unsafe { alloc.make_writable(ptr, layout) }),
+        };
 
-        // Safely drop the value in place
-        unsafe { ptr::drop_in_place(pointer) };
+        if writable.is_ok() {
+            // Safely drop the value in place, then erase what it left behind
+            unsafe { ptr::drop_in_place(self.pointer.as_ptr()) };
+            let _ = self.protect(|_, ptr, layout| {
+                unsafe { util::wipe(ptr, layout.size()) };
+                Ok(())
+            });
+        }
 
         // Deallocate the memory
-        let _ = secret_alloc.dealloc(pointer as _, Layout::new::<T>());
+        let _ = self.protect(|alloc, ptr, layout| unsafe { alloc.dealloc(ptr, layout) });
+    }
+}
+
+impl<T: ?Sized + Zeroize, A: SecretAllocator> Zeroize for SecretBox<T, Unlocked, A> {
+    #[inline]
+    fn zeroize(&mut self) {
+        (**self).zeroize();
+    }
+}
+
+/// The memory of a `SecretBox` is always erased when it is dropped,
+/// regardless of its state and of the allocator in use.
+impl<T: ?Sized, L: State, A: SecretAllocator> ZeroizeOnDrop for SecretBox<T, L, A> {}
+
+/// A guard which allows modifications to the contents of a locked
+/// [`SecretBox`], created by [`SecretBox::write`].
+///
+/// The read-only protection is restored when the guard is dropped. If the
+/// protection cannot be restored, dropping the guard panics (or aborts the
+/// process if it is already unwinding) instead of leaving the memory writable.
+pub struct WriteGuard<'a, T: ?Sized, A: SecretAllocator = Platform> {
+    secret: &'a mut SecretBox<T, Locked, A>,
+}
+
+impl<T: ?Sized, A: SecretAllocator> WriteGuard<'_, T, A> {
+    /// Consumes the guard, restoring the read-only protection.
+    ///
+    /// # Errors
+    /// Returns an `io::Error` if the read-only protection cannot be restored.
+    /// In this case the memory is left writable.
+    pub fn finish(self) -> io::Result<()> {
+        let this = ManuallyDrop::new(self);
+        this.relock()
+    }
+
+    /// Restores the read-only protection of the secret memory.
+    #[inline]
+    fn relock(&self) -> io::Result<()> {
+        self.secret
+            .protect(|alloc, ptr, layout| unsafe { alloc.make_read_only(ptr, layout) })
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> Deref for WriteGuard<'_, T, A> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.secret.pointer.as_ptr() }
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> DerefMut for WriteGuard<'_, T, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.secret.pointer.as_ptr() }
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> fmt::Debug for WriteGuard<'_, T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteGuard").finish_non_exhaustive()
+    }
+}
+
+impl<T: ?Sized, A: SecretAllocator> Drop for WriteGuard<'_, T, A> {
+    fn drop(&mut self) {
+        if let Err(e) = self.relock() {
+            // Never leave the memory writable, a second panic would abort anyway
+            if thread::panicking() {
+                process::abort();
+            }
+
+            panic!("Unable to restore secret memory protection: {e}");
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashSet;
+    use crate::SecretString;
 
     #[test]
     fn test_secretbox_lock() {
@@ -221,6 +1015,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_secretbox_seal() {
+        // Seal the SecretBox
+        let secret = SecretBox::new([0x42u8; 32]);
+        let sealed_secret = secret.seal().expect("Failed to seal SecretBox");
+
+        // Unseal it to read the contents
+        let locked_secret = sealed_secret.unseal().expect("Failed to unseal SecretBox");
+        assert_eq!(
+            *locked_secret, [0x42u8; 32],
+            "Unsealed SecretBox should return the correct value"
+        );
+
+        // Seal it again and unlock it to modify the contents
+        let sealed_secret = locked_secret.seal().expect("Failed to re-seal SecretBox");
+        let mut unlocked_secret = sealed_secret.unlock().expect("Failed to unlock SecretBox");
+        unlocked_secret[0] = 0;
+        assert_eq!(unlocked_secret[..2], [0, 0x42]);
+    }
+
+    #[test]
+    fn test_secretbox_with_mut() {
+        let mut secret = SecretBox::new([0u8; 4])
+            .lock()
+            .expect("Failed to lock SecretBox");
+
+        let result = secret.with_mut(|bytes| {
+            bytes.copy_from_slice(b"abcd");
+            bytes.len()
+        });
+        assert_eq!(result.ok(), Some(4));
+        assert_eq!(&*secret, b"abcd", "SecretBox should contain the new value");
+
+        {
+            let mut guard = secret.write().expect("Failed to unlock SecretBox");
+            guard[0] = b'x';
+        }
+        assert_eq!(&*secret, b"xbcd", "SecretBox should contain the new value");
+
+        let guard = secret.write().expect("Failed to unlock SecretBox");
+        assert!(
+            guard.finish().is_ok(),
+            "Guard should restore the protection"
+        );
+    }
+
+    #[test]
+    fn test_secretbox_with_mut_unwind() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let mut secret = SecretBox::new(42u64)
+            .lock()
+            .expect("Failed to lock SecretBox");
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            secret.with_mut(|value| {
+                *value = 100;
+                panic!("Something went wrong");
+            })
+        }));
+        assert!(result.is_err());
+
+        // The protection has been restored, the box is still usable
+        assert_eq!(*secret, 100);
+        let unlocked_secret = secret.unlock().expect("Failed to unlock SecretBox");
+        assert_eq!(*unlocked_secret, 100);
+    }
+
     #[test]
     fn test_secretbox_eq() {
         let secret1 = SecretBox::new(42);
@@ -238,6 +1100,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "no-ord-hash"))]
     fn test_secretbox_ord() {
         let secret1 = SecretBox::new(30);
         let secret2 = SecretBox::new(40);
@@ -262,7 +1125,10 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "no-ord-hash"))]
     fn test_secretbox_hash() {
+        use std::collections::HashSet;
+
         let secret1 = SecretBox::new(42);
         let secret2 = SecretBox::new(42);
         let secret3 = SecretBox::new(100);
@@ -279,6 +1145,140 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_secretbox_try_new() {
+        let secret = SecretBox::try_new(42).expect("Failed to allocate SecretBox");
+        assert_eq!(*secret, 42, "SecretBox should contain the given value");
+
+        let secret = SecretBox::<u64>::try_default().expect("Failed to allocate SecretBox");
+        assert_eq!(*secret, 0, "SecretBox should contain the default value");
+    }
+
+    #[test]
+    fn test_secretbox_new_in() {
+        use core::{
+            ptr::NonNull,
+            sync::atomic::{AtomicUsize, Ordering},
+        };
+        use std::io;
+
+        #[derive(Default)]
+        struct CountingAllocator(AtomicUsize);
+
+        unsafe impl SecretAllocator for CountingAllocator {
+            fn alloc(&self, layout: Layout) -> io::Result<NonNull<u8>> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Platform.alloc(layout)
+            }
+
+            unsafe fn make_read_only(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Platform.make_read_only(ptr, layout)
+            }
+
+            unsafe fn make_writable(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Platform.make_writable(ptr, layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Platform.dealloc(ptr, layout)
+            }
+        }
+
+        let secret_alloc = CountingAllocator::default();
+
+        let secret = SecretBox::new_in(42, &secret_alloc);
+        let locked_secret = secret.lock().expect("Failed to lock SecretBox");
+        assert_eq!(
+            *locked_secret, 42,
+            "SecretBox should contain the given value"
+        );
+
+        let unlocked_secret = locked_secret.unlock().expect("Failed to unlock SecretBox");
+        drop(unlocked_secret);
+
+        // alloc, make_read_only, make_writable, and dealloc on drop
+        assert_eq!(
+            secret_alloc.0.load(Ordering::Relaxed),
+            4,
+            "SecretBox should route every operation through its allocator"
+        );
+
+        // Dropping a locked box makes it writable first
+        let locked_secret = SecretBox::new_in(42, &secret_alloc).lock().unwrap();
+        drop(locked_secret);
+        assert_eq!(secret_alloc.0.load(Ordering::Relaxed), 8);
+    }
+
+    #[test]
+    fn test_secretbox_slice() {
+        let secret = SecretBox::from_slice_copy(b"super_secret_password");
+        assert_eq!(&*secret, b"super_secret_password");
+
+        let mut secret = SecretBox::<[u8]>::new_zeroed_slice(32);
+        assert_eq!(&*secret, &[0u8; 32]);
+
+        secret[0] = 0x42;
+        let locked_secret = secret.lock().expect("Failed to lock SecretBox");
+        assert_eq!(locked_secret[0], 0x42);
+        assert_eq!(locked_secret.len(), 32);
+
+        let empty_secret = SecretBox::<[u64]>::from_slice_copy(&[]);
+        let empty_secret = empty_secret.lock().expect("Failed to lock SecretBox");
+        assert!(empty_secret.is_empty());
+    }
+
+    #[test]
+    fn test_secretbox_allocated_size() {
+        let secret = SecretBox::new([0u8; 32]);
+        assert!(SecretBox::allocated_size(&secret) >= crate::alloc::page_size());
+
+        let empty_secret = SecretBox::<[u64]>::from_slice_copy(&[]);
+        assert_eq!(SecretBox::allocated_size(&empty_secret), 0);
+
+        let slab = crate::alloc::SlabSecretAllocator::new();
+        let secret = SecretBox::new_in([0u8; 32], &slab);
+        assert_eq!(SecretBox::allocated_size(&secret), 32);
+    }
+
+    #[test]
+    fn test_secretbox_str() {
+        let secret: SecretBox<str> = "super_secret_password".parse().unwrap();
+        assert_eq!(&*secret, "super_secret_password");
+
+        let other_secret = SecretBox::<str>::from_str("super_secret_password").unwrap();
+        let locked_secret = secret.lock().expect("Failed to lock SecretBox");
+        let other_secret = other_secret.lock().expect("Failed to lock SecretBox");
+        assert_eq!(
+            locked_secret, other_secret,
+            "SecretBoxes with equal strings should be equal"
+        );
+
+        let secret = SecretBox::<str>::from("super_secret_password");
+        assert_eq!(&*secret, "super_secret_password");
+        let secret = SecretBox::try_from_str("super_secret_password").unwrap();
+        assert_eq!(&*secret, "super_secret_password");
+
+        let slab = crate::alloc::SlabSecretAllocator::new();
+        let secret = SecretBox::from_str_in("super_secret_password", &slab);
+        assert_eq!(&*secret, "super_secret_password");
+    }
+
+    #[test]
+    fn test_secretbox_new_shallow() {
+        let secret = SecretBox::new_shallow(String::from("super_secret_password"));
+        assert_eq!(
+            secret.as_str(),
+            "super_secret_password",
+            "Shallow SecretBox should contain the given value"
+        );
+
+        let nested_secret = SecretBox::new(SecretString::from("super_secret_password"));
+        assert_eq!(nested_secret.as_str(), "super_secret_password");
+    }
+
     #[test]
     fn test_secretbox_deref() {
         let secret = SecretBox::new(100);
@@ -294,4 +1294,109 @@ mod tests {
         *secret = 200;
         assert_eq!(*secret, 200, "SecretBox should allow mutable dereference");
     }
+
+    #[test]
+    fn test_secretbox_new_with() {
+        let secret = SecretBox::<[u8; 64]>::new_with(|slot| {
+            let key = slot.as_mut_ptr().cast::<u8>();
+            for i in 0..64 {
+                unsafe { key.add(i).write(i as u8) };
+            }
+            unsafe { slot.assume_init_mut() }
+        });
+        assert!(secret.iter().enumerate().all(|(i, &b)| b == i as u8));
+    }
+
+    #[test]
+    #[should_panic(expected = "The initializer must return the slot it was given")]
+    fn test_secretbox_new_with_foreign_slot() {
+        let _ = SecretBox::<u64>::new_with(|_| Box::leak(Box::new(42)));
+    }
+
+    #[test]
+    fn test_secretbox_new_zeroed() {
+        let mut secret = SecretBox::<[u32; 8]>::new_zeroed();
+        unsafe { secret.assume_init_mut()[3] = 7 };
+
+        let secret = unsafe { secret.assume_init() };
+        assert_eq!(*secret, [0, 0, 0, 7, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_secretbox_from_mut() {
+        let mut key = [0xAAu8; 32];
+        let secret = SecretBox::from_mut(&mut key);
+        assert_eq!(
+            *secret, [0xAA; 32],
+            "SecretBox should contain the source value"
+        );
+        assert_eq!(key, [0; 32], "The source value should have been zeroized");
+    }
+
+    #[test]
+    fn test_secretbox_drop_protected() {
+        // The destructor of `Zeroizing` writes to the (protected) memory
+        let secret = SecretBox::new_shallow(zeroize::Zeroizing::new([0xAAu8; 32]));
+        drop(secret.lock().expect("Failed to lock SecretBox"));
+
+        let secret = SecretBox::new_shallow(zeroize::Zeroizing::new([0xAAu8; 32]));
+        drop(secret.seal().expect("Failed to seal SecretBox"));
+    }
+
+    #[test]
+    fn test_secretbox_wipe() {
+        let mut secret = SecretBox::new([0xAAu8; 32]);
+        secret.wipe();
+        assert_eq!(*secret, [0; 32], "Unlocked SecretBox should be wiped");
+
+        let mut secret = SecretBox::new([0xAAu8; 32]).lock().unwrap();
+        secret.wipe().expect("Failed to wipe locked SecretBox");
+        assert_eq!(*secret, [0; 32], "Locked SecretBox should be wiped");
+
+        let mut secret = SecretBox::new([0xAAu8; 32]).seal().unwrap();
+        secret.wipe().expect("Failed to wipe sealed SecretBox");
+        let secret = secret.unseal().unwrap();
+        assert_eq!(*secret, [0; 32], "Sealed SecretBox should be wiped");
+    }
+
+    #[test]
+    fn test_secretbox_ct() {
+        let mut secret = SecretBox::new([0u8; 16]);
+        assert!(secret.ct_is_zero(), "Zeroed SecretBox should be zero");
+
+        secret.ct_select(&[0xAA; 16], false);
+        assert!(secret.ct_is_zero(), "SecretBox should be left untouched");
+
+        secret.ct_select(&[0xAA; 16], true);
+        assert!(secret.ct_eq(&[0xAA; 16]), "SecretBox should be replaced");
+        assert!(!secret.ct_eq(&[0xAB; 16]));
+
+        let slice_secret = SecretBox::from_slice_copy(&[1u32, 2, 3]);
+        assert!(slice_secret.ct_eq(&[1, 2, 3]));
+        assert!(!slice_secret.ct_eq(&[1, 2]));
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_secretbox_drop_forked() {
+        // Forking within the multithreaded test harness is not safe
+        if !crate::util::isolate("boxed::tests::test_secretbox_drop_forked", &[]) {
+            return;
+        }
+
+        // The pages may be left out of the child, which drops them anyway
+        let s = SecretBox::new([7u8; 32]);
+        match unsafe { libc::fork() } {
+            0 => {
+                drop(s);
+                unsafe { libc::_exit(0) };
+            }
+            pid => {
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                assert!(libc::WIFEXITED(status), "Child should not fault");
+                assert_eq!(libc::WEXITSTATUS(status), 0);
+            }
+        }
+    }
 }
@@ -0,0 +1,259 @@
+use core::fmt;
+
+use crate::alloc::{self, AllocatorKind, Config};
+
+/// A report on how secrets are protected in the current process, returned by
+/// [`capabilities`].
+///
+/// Its `Display` implementation renders a single line, suited for startup
+/// banners and logs.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// The allocator picked by [`platform_secret_allocator`](alloc::platform_secret_allocator).
+    pub allocator: AllocatorKind,
+    /// The availability of `memfd_secret`.
+    pub memfd_secret: MemfdSecret,
+    /// The `RLIMIT_MEMLOCK` resource limit, if the platform has one.
+    pub memlock_limit: Option<MemlockLimit>,
+    /// Whether secret pages are left out of core dumps, either by the
+    /// allocator in use or because the process cannot dump core at all.
+    pub core_dumps_excluded: bool,
+    /// Whether the process cannot dump core at all, because its `RLIMIT_CORE`
+    /// limit is `0` or it is not dumpable (e.g. after [`harden_process`](crate::harden_process)).
+    pub core_dumps_disabled: bool,
+    /// The size of a memory page, in bytes.
+    pub page_size: usize,
+    /// The configuration of the platform allocator, or `None` if it could
+    /// not be configured.
+    pub config: Option<Config>,
+}
+
+/// The availability of the `memfd_secret` system call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemfdSecret {
+    /// `memfd_secret` is available.
+    Available,
+    /// The kernel supports `memfd_secret`, but it has been disabled
+    /// (e.g. with the `secretmem.enable=0` boot parameter).
+    Disabled,
+    /// The platform or kernel does not support `memfd_secret`.
+    Unsupported,
+}
+
+/// The current (soft) and maximum (hard) values of `RLIMIT_MEMLOCK`,
+/// in bytes, where `None` stands for an unlimited value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemlockLimit {
+    /// The current (soft) limit.
+    pub current: Option<u64>,
+    /// The maximum (hard) limit.
+    pub max: Option<u64>,
+}
+
+/// Returns a report on how secrets are protected in the current process.
+///
+/// This initializes the platform allocator if it has not been used yet, so any
+/// [`Config`] should be installed before calling this function.
+pub fn capabilities() -> Capabilities {
+    let instance = alloc::platform_instance();
+    let core_dumps_disabled = core_dumps_disabled();
+
+    Capabilities {
+        allocator: instance.kind,
+        memfd_secret: memfd_secret(),
+        memlock_limit: memlock_limit(),
+        core_dumps_excluded: match instance.kind {
+            // Secret mappings are advised with `MADV_DONTDUMP` (`MADV_NOCORE`),
+            // and `memfd_secret` pages never appear in core dumps.
+            AllocatorKind::MemfdSecret | AllocatorKind::Mlock => true,
+            // `VirtualLock`ed pages are included in crash dumps
+            AllocatorKind::VirtualLock | AllocatorKind::Unavailable => core_dumps_disabled,
+        },
+        core_dumps_disabled,
+        page_size: alloc::page_size(),
+        config: instance.config.clone(),
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "allocator={} memfd_secret={}",
+            self.allocator, self.memfd_secret
+        )?;
+
+        if let Some(limit) = self.memlock_limit {
+            let fmt_limit = |limit: Option<u64>| match limit {
+                Some(limit) => limit.to_string(),
+                None => "unlimited".into(),
+            };
+            write!(
+                f,
+                " memlock={}/{}",
+                fmt_limit(limit.current),
+                fmt_limit(limit.max)
+            )?;
+        }
+
+        write!(
+            f,
+            " core_dumps_excluded={} core_dumps_disabled={} page_size={}",
+            self.core_dumps_excluded, self.core_dumps_disabled, self.page_size
+        )?;
+
+        if let Some(config) = &self.config {
+            write!(
+                f,
+                " backend={} mlock_failure={} fork_policy={} guard_pages={} canaries={}",
+                config.get_backend(),
+                config.get_mlock_failure(),
+                config.get_fork_policy(),
+                config.get_guard_pages(),
+                config.get_canaries()
+            )?;
+        }
+
+        if let Some(config) = self.config.as_ref().filter(|config| config.get_canaries()) {
+            write!(f, " canary_failure={}", config.get_canary_failure())?;
+        }
+
+        if let Some(quota) = self.config.as_ref().and_then(Config::get_memlock_quota) {
+            write!(f, " memlock_quota={quota}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for MemfdSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Available => "available",
+            Self::Disabled => "disabled",
+            Self::Unsupported => "unsupported",
+        })
+    }
+}
+
+/// Probes the availability of `memfd_secret`.
+#[cfg(target_os = "linux")]
+fn memfd_secret() -> MemfdSecret {
+    match unsafe { libc::syscall(libc::SYS_memfd_secret, 0) } {
+        -1 => {
+            // The kernel rejects the call with `ENOSYS` when the feature has
+            // been disabled, the module parameter tells the two cases apart.
+            match std::fs::read_to_string("/sys/module/secretmem/parameters/enable") {
+                Ok(enable) if matches!(enable.trim(), "N" | "n" | "0") => MemfdSecret::Disabled,
+                _ => MemfdSecret::Unsupported,
+            }
+        }
+        fd => {
+            unsafe { libc::close(fd as libc::c_int) };
+            MemfdSecret::Available
+        }
+    }
+}
+
+/// Probes the availability of `memfd_secret`.
+#[cfg(not(target_os = "linux"))]
+fn memfd_secret() -> MemfdSecret {
+    MemfdSecret::Unsupported
+}
+
+/// Returns whether the process cannot dump core at all.
+#[cfg(target_family = "unix")]
+fn core_dumps_disabled() -> bool {
+    let mut rlimit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+
+    if unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut rlimit) } == 0 && rlimit.rlim_cur == 0 {
+        return true;
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if unsafe { libc::prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0) } == 0 {
+        return true;
+    }
+
+    false
+}
+
+/// Returns whether the process cannot dump core at all.
+#[cfg(not(target_family = "unix"))]
+fn core_dumps_disabled() -> bool {
+    false
+}
+
+/// Reads the `RLIMIT_MEMLOCK` resource limit.
+#[cfg(target_family = "unix")]
+pub(crate) fn memlock_limit() -> Option<MemlockLimit> {
+    let mut rlimit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+
+    if unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut rlimit) } < 0 {
+        return None;
+    }
+
+    // `rlim_t` is not `u64` on every platform
+    #[allow(clippy::unnecessary_cast)]
+    let limit = |value: libc::rlim_t| match value {
+        libc::RLIM_INFINITY => None,
+        value => Some(value as u64),
+    };
+
+    Some(MemlockLimit {
+        current: limit(rlimit.rlim_cur),
+        max: limit(rlimit.rlim_max),
+    })
+}
+
+/// Reads the `RLIMIT_MEMLOCK` resource limit.
+#[cfg(not(target_family = "unix"))]
+pub(crate) fn memlock_limit() -> Option<MemlockLimit> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities() {
+        let capabilities = capabilities();
+        assert!(capabilities.page_size.is_power_of_two());
+
+        #[cfg(target_os = "linux")]
+        if capabilities
+            .config
+            .as_ref()
+            .is_some_and(|config| config.get_backend() == alloc::Backend::Memfd)
+        {
+            assert_eq!(
+                capabilities.allocator == AllocatorKind::MemfdSecret,
+                capabilities.memfd_secret == MemfdSecret::Available,
+                "memfd_secret should be picked whenever it is available"
+            );
+        }
+
+        #[cfg(target_family = "unix")]
+        assert!(capabilities.memlock_limit.is_some());
+
+        if matches!(
+            capabilities.allocator,
+            AllocatorKind::MemfdSecret | AllocatorKind::Mlock
+        ) || capabilities.core_dumps_disabled
+        {
+            assert!(capabilities.core_dumps_excluded);
+        }
+
+        let banner = capabilities.to_string();
+        assert!(banner.starts_with("allocator="));
+        assert!(banner.contains(&format!("page_size={}", capabilities.page_size)));
+    }
+}
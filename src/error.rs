@@ -0,0 +1,299 @@
+use core::fmt;
+use std::{error, io};
+
+/// A specialized `Result` type for fallible secret memory operations.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// The error type returned when a secret memory region cannot be set up.
+///
+/// Each variant describes the cause of the failure and keeps the underlying
+/// `io::Error` reported by the [`SecretAllocator`](crate::alloc::SecretAllocator).
+#[derive(Debug)]
+pub enum Error {
+    /// The amount of locked memory allowed for the process (e.g. `RLIMIT_MEMLOCK`
+    /// on Unix systems, the working set quota on Windows) has been exceeded.
+    LockLimitExceeded(io::Error),
+    /// The secret memory backend is not supported by the running system.
+    Unsupported(io::Error),
+    /// The access permissions of the secret memory region could not be changed.
+    ProtectionFailed(io::Error),
+    /// The system has run out of memory.
+    OutOfMemory(io::Error),
+    /// The per-process quota on locked memory, set with
+    /// [`Config::memlock_quota`](crate::alloc::Config::memlock_quota), has been exceeded.
+    QuotaExceeded(io::Error),
+    /// A secret memory region that was no longer needed could not be released
+    /// (e.g. its canary did not match, or it could not be unmapped).
+    ReleaseFailed(io::Error),
+}
+
+impl Error {
+    /// Classifies an `io::Error` returned by a failed secret memory allocation.
+    pub(crate) fn from_alloc(error: io::Error) -> Self {
+        if crate::alloc::is_quota_exceeded(&error) {
+            return Self::QuotaExceeded(error);
+        }
+
+        // Errors reported while locking the pages are classified on their own,
+        // since some codes (e.g. `ENOMEM`) mean something else elsewhere.
+        let locking = crate::alloc::take_lock_failure(&error);
+
+        if let Some(code) = error.raw_os_error() {
+            #[cfg(target_family = "unix")]
+            match code {
+                libc::ENOMEM if locking => return Self::LockLimitExceeded(error),
+                libc::EAGAIN | libc::EPERM => return Self::LockLimitExceeded(error),
+                libc::ENOSYS | libc::EOPNOTSUPP => return Self::Unsupported(error),
+                libc::EACCES => return Self::ProtectionFailed(error),
+                _ => {}
+            }
+
+            #[cfg(target_family = "windows")]
+            match code as u32 {
+                windows_sys::Win32::Foundation::ERROR_WORKING_SET_QUOTA => {
+                    return Self::LockLimitExceeded(error)
+                }
+                windows_sys::Win32::Foundation::ERROR_NOT_SUPPORTED => {
+                    return Self::Unsupported(error)
+                }
+                _ if locking => return Self::LockLimitExceeded(error),
+                _ => {}
+            }
+        }
+
+        match error.kind() {
+            io::ErrorKind::Unsupported => Self::Unsupported(error),
+            io::ErrorKind::PermissionDenied => Self::ProtectionFailed(error),
+            _ => Self::OutOfMemory(error),
+        }
+    }
+
+    /// Returns a reference to the underlying `io::Error`.
+    pub fn io_error(&self) -> &io::Error {
+        match self {
+            Self::LockLimitExceeded(e)
+            | Self::Unsupported(e)
+            | Self::ProtectionFailed(e)
+            | Self::OutOfMemory(e)
+            | Self::QuotaExceeded(e)
+            | Self::ReleaseFailed(e) => e,
+        }
+    }
+
+    /// Consumes the `Error`, returning the underlying `io::Error`.
+    pub fn into_io_error(self) -> io::Error {
+        match self {
+            Self::LockLimitExceeded(e)
+            | Self::Unsupported(e)
+            | Self::ProtectionFailed(e)
+            | Self::OutOfMemory(e)
+            | Self::QuotaExceeded(e)
+            | Self::ReleaseFailed(e) => e,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The `io::Error` is left to `source`, so that error reports do not
+        // print it twice.
+        f.write_str(match self {
+            Self::LockLimitExceeded(_) => "locked memory limit exceeded",
+            Self::Unsupported(_) => "secret memory backend unsupported",
+            Self::ProtectionFailed(_) => "unable to change secret memory protection",
+            Self::OutOfMemory(_) => "out of memory",
+            Self::QuotaExceeded(_) => "secret memory quota exceeded",
+            Self::ReleaseFailed(_) => "unable to release secret memory",
+        })
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(self.io_error())
+    }
+}
+
+impl From<Error> for io::Error {
+    #[inline]
+    fn from(error: Error) -> Self {
+        error.into_io_error()
+    }
+}
+
+/// The error type returned when a secret container cannot be locked.
+///
+/// It carries the original container, which can be recovered with
+/// [`LockError::into_inner`], and the `io::Error` reported while changing
+/// the memory protection.
+pub struct LockError<S> {
+    secret: S,
+    source: io::Error,
+}
+
+impl<S> LockError<S> {
+    #[inline]
+    pub(crate) fn new(secret: S, source: io::Error) -> Self {
+        Self { secret, source }
+    }
+
+    /// Returns a reference to the `io::Error` that caused the failure.
+    #[inline]
+    pub fn io_error(&self) -> &io::Error {
+        &self.source
+    }
+
+    /// Consumes the error, returning the original secret container.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.secret
+    }
+
+    /// Consumes the error, returning the original secret container and
+    /// the `io::Error` that caused the failure.
+    #[inline]
+    pub fn into_parts(self) -> (S, io::Error) {
+        (self.secret, self.source)
+    }
+}
+
+impl<S> fmt::Debug for LockError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockError")
+            .field("source", &self.source)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> fmt::Display for LockError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to lock secret memory")
+    }
+}
+
+impl<S> error::Error for LockError<S> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// The error type returned when a secret container cannot be unlocked.
+///
+/// It carries the original container, which can be recovered with
+/// [`UnlockError::into_inner`], and the `io::Error` reported while changing
+/// the memory protection.
+pub struct UnlockError<S> {
+    secret: S,
+    source: io::Error,
+}
+
+impl<S> UnlockError<S> {
+    #[inline]
+    pub(crate) fn new(secret: S, source: io::Error) -> Self {
+        Self { secret, source }
+    }
+
+    /// Returns a reference to the `io::Error` that caused the failure.
+    #[inline]
+    pub fn io_error(&self) -> &io::Error {
+        &self.source
+    }
+
+    /// Consumes the error, returning the original secret container.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.secret
+    }
+
+    /// Consumes the error, returning the original secret container and
+    /// the `io::Error` that caused the failure.
+    #[inline]
+    pub fn into_parts(self) -> (S, io::Error) {
+        (self.secret, self.source)
+    }
+}
+
+impl<S> fmt::Debug for UnlockError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UnlockError")
+            .field("source", &self.source)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> fmt::Display for UnlockError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unable to unlock secret memory")
+    }
+}
+
+impl<S> error::Error for UnlockError<S> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl<S> From<LockError<S>> for io::Error {
+    #[inline]
+    fn from(error: LockError<S>) -> Self {
+        error.source
+    }
+}
+
+impl<S> From<UnlockError<S>> for io::Error {
+    #[inline]
+    fn from(error: UnlockError<S>) -> Self {
+        error.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_from_alloc() {
+        let error = Error::from_alloc(io::Error::from(io::ErrorKind::OutOfMemory));
+        assert!(matches!(error, Error::OutOfMemory(_)));
+
+        let error = Error::from_alloc(io::Error::from(io::ErrorKind::Unsupported));
+        assert!(matches!(error, Error::Unsupported(_)));
+
+        #[cfg(target_family = "unix")]
+        {
+            let error = Error::from_alloc(io::Error::from_raw_os_error(libc::EAGAIN));
+            assert!(matches!(error, Error::LockLimitExceeded(_)));
+
+            let error = Error::from_alloc(io::Error::from_raw_os_error(libc::ENOSYS));
+            assert!(matches!(error, Error::Unsupported(_)));
+
+            let error = Error::from_alloc(io::Error::from_raw_os_error(libc::ENOMEM));
+            assert!(matches!(error, Error::OutOfMemory(_)));
+        }
+    }
+
+    #[test]
+    fn test_error_source() {
+        use std::error::Error as _;
+
+        let error = Error::from_alloc(io::Error::new(io::ErrorKind::OutOfMemory, "no pages"));
+        assert!(error.source().is_some());
+        assert_eq!(error.io_error().kind(), io::ErrorKind::OutOfMemory);
+        // The cause is only reported through `source`
+        assert_eq!(error.to_string(), "out of memory");
+    }
+
+    #[test]
+    fn test_lock_error_parts() {
+        use std::error::Error as _;
+
+        let error = LockError::new(42, io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(error.source().is_some());
+        assert_eq!(error.to_string(), "unable to lock secret memory");
+        assert_eq!(error.io_error().kind(), io::ErrorKind::PermissionDenied);
+
+        let (secret, source) = error.into_parts();
+        assert_eq!(secret, 42);
+        assert_eq!(source.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
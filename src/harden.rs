@@ -0,0 +1,355 @@
+use core::fmt;
+use std::io;
+
+/// The process-wide hardening measures applied by [`harden_process`].
+///
+/// Only [`non_dumpable`](HardenOptions::non_dumpable) and
+/// [`no_core_dumps`](HardenOptions::no_core_dumps) are enabled by default.
+///
+/// # Example
+/// ```
+/// use secret_mem::HardenOptions;
+///
+/// let report = secret_mem::harden_process(HardenOptions::new());
+/// println!("{report}");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HardenOptions {
+    non_dumpable: bool,
+    no_core_dumps: bool,
+    lock_all_memory: bool,
+    no_new_privs: bool,
+}
+
+impl HardenOptions {
+    /// Creates a new `HardenOptions` with the default measures.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables marking the process as non-dumpable with
+    /// `prctl(PR_SET_DUMPABLE, 0)` (Linux only), which prevents core dumps
+    /// and the attachment of debuggers by unprivileged users, and makes the
+    /// `/proc/<pid>` files of the process owned by root.
+    #[inline]
+    pub fn non_dumpable(mut self, enabled: bool) -> Self {
+        self.non_dumpable = enabled;
+        self
+    }
+
+    /// Enables or disables setting both the current and maximum `RLIMIT_CORE`
+    /// limits to `0` (Unix only), so that the process never writes a core dump.
+    #[inline]
+    pub fn no_core_dumps(mut self, enabled: bool) -> Self {
+        self.no_core_dumps = enabled;
+        self
+    }
+
+    /// Enables or disables locking every present and future page of the
+    /// process into RAM with `mlockall` (Unix only).
+    ///
+    /// On Linux, pages are only locked once they are first accessed
+    /// (`MCL_ONFAULT`, from Linux 4.4). Every locked page counts against
+    /// `RLIMIT_MEMLOCK`, which then also limits the memory available to the
+    /// secret allocators: this measure is disabled by default.
+    #[inline]
+    pub fn lock_all_memory(mut self, enabled: bool) -> Self {
+        self.lock_all_memory = enabled;
+        self
+    }
+
+    /// Enables or disables `prctl(PR_SET_NO_NEW_PRIVS, 1)` (Linux only), which
+    /// prevents `execve` from granting privileges (e.g. through setuid
+    /// binaries).
+    ///
+    /// This measure applies to the calling thread, and to the threads and
+    /// processes it creates afterwards: [`harden_process`] should be called
+    /// from the main thread before any other thread is spawned. It also
+    /// breaks programs relying on setuid helpers (e.g. `sudo`), and is
+    /// therefore disabled by default.
+    #[inline]
+    pub fn no_new_privs(mut self, enabled: bool) -> Self {
+        self.no_new_privs = enabled;
+        self
+    }
+
+    /// Returns whether the process is marked as non-dumpable.
+    #[inline]
+    pub fn get_non_dumpable(&self) -> bool {
+        self.non_dumpable
+    }
+
+    /// Returns whether `RLIMIT_CORE` is set to `0`.
+    #[inline]
+    pub fn get_no_core_dumps(&self) -> bool {
+        self.no_core_dumps
+    }
+
+    /// Returns whether every page of the process is locked into RAM.
+    #[inline]
+    pub fn get_lock_all_memory(&self) -> bool {
+        self.lock_all_memory
+    }
+
+    /// Returns whether `PR_SET_NO_NEW_PRIVS` is set.
+    #[inline]
+    pub fn get_no_new_privs(&self) -> bool {
+        self.no_new_privs
+    }
+}
+
+impl Default for HardenOptions {
+    fn default() -> Self {
+        Self {
+            non_dumpable: true,
+            no_core_dumps: true,
+            lock_all_memory: false,
+            no_new_privs: false,
+        }
+    }
+}
+
+/// The outcome of each hardening measure, returned by [`harden_process`].
+///
+/// Its `Display` implementation renders a single line, suited for logs.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct HardenReport {
+    /// The outcome of `prctl(PR_SET_DUMPABLE, 0)`.
+    pub non_dumpable: HardenStatus,
+    /// The outcome of setting `RLIMIT_CORE` to `0`.
+    pub no_core_dumps: HardenStatus,
+    /// The outcome of `mlockall`.
+    pub lock_all_memory: HardenStatus,
+    /// The outcome of `prctl(PR_SET_NO_NEW_PRIVS, 1)`.
+    pub no_new_privs: HardenStatus,
+}
+
+/// The outcome of a hardening measure.
+#[derive(Debug)]
+pub enum HardenStatus {
+    /// The measure was not requested.
+    Skipped,
+    /// The measure has been applied.
+    Applied,
+    /// The measure could not be applied, an `io::Error` of kind `Unsupported`
+    /// standing for a measure that the platform does not provide.
+    Failed(io::Error),
+}
+
+impl HardenReport {
+    /// Returns `true` if every requested measure has been applied.
+    pub fn is_complete(&self) -> bool {
+        [
+            &self.non_dumpable,
+            &self.no_core_dumps,
+            &self.lock_all_memory,
+            &self.no_new_privs,
+        ]
+        .into_iter()
+        .all(|status| !matches!(status, HardenStatus::Failed(_)))
+    }
+}
+
+impl HardenStatus {
+    /// Returns `true` if the measure has been applied.
+    #[inline]
+    pub fn is_applied(&self) -> bool {
+        matches!(self, Self::Applied)
+    }
+
+    fn from_result(enabled: bool, apply: impl FnOnce() -> io::Result<()>) -> Self {
+        match enabled {
+            false => Self::Skipped,
+            true => match apply() {
+                Ok(_) => Self::Applied,
+                Err(error) => Self::Failed(error),
+            },
+        }
+    }
+}
+
+impl fmt::Display for HardenReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "non_dumpable={} no_core_dumps={} lock_all_memory={} no_new_privs={}",
+            self.non_dumpable, self.no_core_dumps, self.lock_all_memory, self.no_new_privs
+        )
+    }
+}
+
+impl fmt::Display for HardenStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Skipped => f.write_str("skipped"),
+            Self::Applied => f.write_str("applied"),
+            Self::Failed(error) => write!(f, "failed ({error})"),
+        }
+    }
+}
+
+/// Applies process-wide hardening measures, which complement the protection
+/// of the secret pages themselves: a secret locked into RAM and excluded from
+/// core dumps can still be read by a debugger attached to the process.
+///
+/// Each measure is attempted independently, the returned report tells which
+/// ones have been applied. None of them can be undone by an unprivileged
+/// process.
+pub fn harden_process(options: HardenOptions) -> HardenReport {
+    HardenReport {
+        non_dumpable: HardenStatus::from_result(options.non_dumpable, set_non_dumpable),
+        no_core_dumps: HardenStatus::from_result(options.no_core_dumps, disable_core_dumps),
+        lock_all_memory: HardenStatus::from_result(options.lock_all_memory, lock_all_memory),
+        no_new_privs: HardenStatus::from_result(options.no_new_privs, set_no_new_privs),
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_non_dumpable() -> io::Result<()> {
+    check(unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_non_dumpable() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "PR_SET_DUMPABLE is not available on this platform",
+    ))
+}
+
+#[cfg(target_family = "unix")]
+fn disable_core_dumps() -> io::Result<()> {
+    let rlimit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+
+    check(unsafe { libc::setrlimit(libc::RLIMIT_CORE, &rlimit) })
+}
+
+#[cfg(not(target_family = "unix"))]
+fn disable_core_dumps() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "RLIMIT_CORE is not available on this platform",
+    ))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn lock_all_memory() -> io::Result<()> {
+    let flags = libc::MCL_CURRENT | libc::MCL_FUTURE;
+
+    match check(unsafe { libc::mlockall(flags | libc::MCL_ONFAULT) }) {
+        // Kernels older than 4.4 do not know `MCL_ONFAULT`
+        Err(e) if e.raw_os_error() == Some(libc::EINVAL) => check(unsafe { libc::mlockall(flags) }),
+        result => result,
+    }
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+fn lock_all_memory() -> io::Result<()> {
+    check(unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) })
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "illumos",
+    target_os = "solaris"
+)))]
+fn lock_all_memory() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "mlockall is not available on this platform",
+    ))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_no_new_privs() -> io::Result<()> {
+    check(unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_no_new_privs() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "PR_SET_NO_NEW_PRIVS is not available on this platform",
+    ))
+}
+
+#[cfg(target_family = "unix")]
+fn check(result: libc::c_int) -> io::Result<()> {
+    match result {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_harden_process() {
+        // The measures cannot be undone, so they are applied in a process of
+        // their own rather than in the test binary
+        if !crate::util::isolate("harden::tests::test_harden_process", &[]) {
+            return;
+        }
+
+        let options = HardenOptions::new();
+        assert!(options.get_non_dumpable() && options.get_no_core_dumps());
+        assert!(!options.get_lock_all_memory() && !options.get_no_new_privs());
+
+        let report = harden_process(options);
+        assert!(matches!(report.lock_all_memory, HardenStatus::Skipped));
+        assert!(matches!(report.no_new_privs, HardenStatus::Skipped));
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            assert!(report.is_complete(), "{report:?}");
+            assert!(report.non_dumpable.is_applied());
+            assert_eq!(unsafe { libc::prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0) }, 0);
+        }
+
+        let mut rlimit = libc::rlimit {
+            rlim_cur: 1,
+            rlim_max: 1,
+        };
+        assert_eq!(
+            unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut rlimit) },
+            0
+        );
+        assert_eq!((rlimit.rlim_cur, rlimit.rlim_max), (0, 0));
+
+        let line = report.to_string();
+        assert!(line.starts_with("non_dumpable="));
+        assert!(line.contains("lock_all_memory=skipped"));
+    }
+
+    #[test]
+    fn test_harden_status_display() {
+        let status = HardenStatus::Failed(io::Error::from_raw_os_error(1));
+        let expected = format!("failed ({})", io::Error::from_raw_os_error(1));
+        assert_eq!(status.to_string(), expected);
+        assert_eq!(HardenStatus::Applied.to_string(), "applied");
+    }
+}
@@ -12,16 +12,43 @@
 //!   - **Linux**: Uses `memfd_secret` for secure memory allocation (_when available_).
 //!   - **Unix**: Uses `mmap` with `MAP_ANON` and `mlock` to prevent memory from being swapped to disk.
 //!   - **Windows**: Uses `VirtualAlloc` with `PAGE_NOCACHE` and `VirtualLock` to secure memory.
+//! - **Configurable Backend**: The platform allocator can be configured through
+//!   [`alloc::Config`] or the `SECRET_MEM_BACKEND` (`memfd`, `mlock` or `strict-memfd`),
+//!   `SECRET_MEM_MLOCK_FAILURE` (`fatal` or `warn`) and `SECRET_MEM_FORK_POLICY` (`inherit`,
+//!   `wipe` or `dont-fork`) environment variables.
+//! - **Fork Hygiene**: Secret pages are wiped in (or left out of) the children of a forking
+//!   process, see [`alloc::ForkPolicy`].
 //! - **Memory Protection**: Provides functions to change memory access permissions, making memory
-//!   regions read-only or writable as needed.
+//!   regions read-only, writable or inaccessible as needed.
 //! - **Secure Deallocation**: Ensures that sensitive data is securely erased before memory is deallocated.
+//! - **Locked Memory Budget**: [`alloc::memlock_budget`] tracks the locked bytes against
+//!   `RLIMIT_MEMLOCK`, which can be raised on initialization, and an optional per-process quota
+//!   on the locked bytes.
+//! - **Slab Allocation**: [`alloc::SlabSecretAllocator`] packs small secrets into shared locked
+//!   pages, instead of spending a whole page per unlocked secret.
+//! - **Usage Statistics**: [`stats`] reports the live allocations, their overhead and the
+//!   protection changes of each backend, for capacity planning.
+//! - **Process Hardening**: [`harden_process`] disables core dumps and debugger attachment for
+//!   the whole process, and can lock all of its memory into RAM.
+//! - **Constant-Time Operations**: Provides constant-time comparison and selection for secret bytes.
+//!
+//! ## Optional Features
+//!
+//! - `ct-eq`: makes `==` on secret containers constant time, restricting it to values
+//!   implementing [`ConstantTimeEq`].
+//! - `serde`: enables deserializing secret containers straight into secret memory, and
+//!   serializing them on an opt-in basis.
+//! - `no-ord-hash`: removes the `PartialOrd`, `Ord` and `Hash` implementations of `SecretBox`,
+//!   which leak information about the secret through their timing and results.
+//! - `unicode-normalization`: enables Unicode NFKC normalization of `SecretString`s.
 //!
 //! ## Safety and Security
 //!
 //! This library aims to provide a high level of security for managing sensitive data. However, it is
 //! important to note that:
 //!
-//! - The current implementation may waste memory due to alignment and page size constraints.
+//! - The platform allocators spend at least a whole page per secret, the slab allocator shares
+//!   pages between unlocked secrets only, as memory protection acts on whole pages.
 //! - The library relies on platform-specific features, which may have different security guarantees.
 //! - Users should ensure that the library is used in a secure environment and follow best practices
 //!   for handling sensitive data.
@@ -30,18 +57,26 @@
 //!
 //! This library is licensed under the MIT/Apache-2.0 license.
 
-mod alloc;
+pub mod alloc;
+mod arc;
 mod boxed;
+mod capabilities;
+mod error;
+mod harden;
+pub mod marker;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod stats;
+mod string;
 mod util;
+mod vec;
 
-pub mod marker {
-    /// Marker type indicating that a secret container is in a locked state,
-    /// where the contents are protected from modification.
-    pub enum Locked {}
-
-    /// Marker type indicating that a secret container is in an unlocked state,
-    /// allowing modification of the contents.
-    pub enum Unlocked {}
-}
-
-pub use boxed::SecretBox;
+pub use arc::{ReadGuard, SecretArc};
+pub use boxed::{SecretBox, WriteGuard};
+pub use capabilities::{capabilities, Capabilities, MemfdSecret, MemlockLimit};
+pub use error::{Error, LockError, Result, UnlockError};
+pub use harden::{harden_process, HardenOptions, HardenReport, HardenStatus};
+pub use stats::{stats, BackendStats, Stats};
+pub use string::SecretString;
+pub use util::ct::ConstantTimeEq;
+pub use vec::SecretVec;
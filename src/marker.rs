@@ -0,0 +1,151 @@
+//! Marker types and traits for secret containers.
+
+use core::{mem::MaybeUninit, num::Wrapping};
+
+use crate::{alloc::SecretAllocator, SecretBox, SecretString, SecretVec};
+
+/// Marker type indicating that a secret container is in a locked state,
+/// where the contents are protected from modification.
+pub enum Locked {}
+
+/// Marker type indicating that a secret container is in an unlocked state,
+/// allowing modification of the contents.
+pub enum Unlocked {}
+
+/// Marker type indicating that a secret container is in a no-access state,
+/// where the contents can be neither read nor modified.
+///
+/// Any access to the underlying memory faults, so secret containers in this
+/// state do not implement `Deref`.
+pub enum NoAccess {}
+
+/// Marker trait for the states of a secret container, namely [`Locked`],
+/// [`Unlocked`] and [`NoAccess`].
+///
+/// This trait is sealed and cannot be implemented outside of this crate.
+pub trait State: private::Sealed {
+    /// Whether the memory of a secret container in this state is writable.
+    #[doc(hidden)]
+    const WRITABLE: bool;
+}
+
+/// Marker trait for the states in which the contents of a secret container
+/// can be read, namely [`Locked`] and [`Unlocked`].
+///
+/// This trait is sealed and cannot be implemented outside of this crate.
+pub trait Readable: State {}
+
+impl private::Sealed for Locked {}
+impl State for Locked {
+    const WRITABLE: bool = false;
+}
+impl Readable for Locked {}
+
+impl private::Sealed for Unlocked {}
+impl State for Unlocked {
+    const WRITABLE: bool = true;
+}
+impl Readable for Unlocked {}
+
+impl private::Sealed for NoAccess {}
+impl State for NoAccess {
+    const WRITABLE: bool = false;
+}
+
+/// Marker trait for types whose whole contents live inline, and are therefore
+/// fully protected once moved into secret memory.
+///
+/// Types that own heap memory (e.g. `String` or `Vec<T>`) only store their
+/// pointer, length and capacity inline, so their actual contents would stay
+/// on the global heap, which is neither locked nor zeroized. Such types do
+/// not implement this trait and are rejected by [`SecretBox::new`] and
+/// [`SecretVec::new`].
+///
+/// This trait is sealed and implemented for primitive types, arrays, slices,
+/// tuples and the secret containers of this crate. Other types can still be
+/// stored in a `SecretBox` or a `SecretVec` through the explicit
+/// [`SecretBox::new_shallow`] and [`SecretVec::new_shallow`] escape hatches.
+///
+/// ```compile_fail
+/// use secret_mem::SecretBox;
+///
+/// let secret = SecretBox::new(String::from("super_secret_password"));
+/// ```
+///
+/// ```compile_fail
+/// use secret_mem::SecretVec;
+///
+/// let mut secrets = SecretVec::new();
+/// secrets.push(String::from("super_secret_password"));
+/// ```
+pub trait SecretSafe: private::Sealed {}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_secret_safe {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl private::Sealed for $ty {}
+            impl SecretSafe for $ty {}
+        )*
+    };
+}
+
+impl_secret_safe!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    str,
+);
+
+macro_rules! impl_secret_safe_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: SecretSafe),+> private::Sealed for ($($name,)+) {}
+        impl<$($name: SecretSafe),+> SecretSafe for ($($name,)+) {}
+    };
+}
+
+impl_secret_safe_tuple!(A);
+impl_secret_safe_tuple!(A, B);
+impl_secret_safe_tuple!(A, B, C);
+impl_secret_safe_tuple!(A, B, C, D);
+
+impl<T: SecretSafe, const N: usize> private::Sealed for [T; N] {}
+impl<T: SecretSafe, const N: usize> SecretSafe for [T; N] {}
+
+impl<T: SecretSafe> private::Sealed for [T] {}
+impl<T: SecretSafe> SecretSafe for [T] {}
+
+impl<T: SecretSafe> private::Sealed for MaybeUninit<T> {}
+impl<T: SecretSafe> SecretSafe for MaybeUninit<T> {}
+
+impl<T: SecretSafe> private::Sealed for Wrapping<T> {}
+impl<T: SecretSafe> SecretSafe for Wrapping<T> {}
+
+impl<T: SecretSafe> private::Sealed for Option<T> {}
+impl<T: SecretSafe> SecretSafe for Option<T> {}
+
+impl<T: ?Sized + SecretSafe, L: State, A: SecretAllocator> private::Sealed for SecretBox<T, L, A> {}
+impl<T: ?Sized + SecretSafe, L: State, A: SecretAllocator> SecretSafe for SecretBox<T, L, A> {}
+
+impl<T: SecretSafe, L: State, A: SecretAllocator> private::Sealed for SecretVec<T, L, A> {}
+impl<T: SecretSafe, L: State, A: SecretAllocator> SecretSafe for SecretVec<T, L, A> {}
+
+impl<L: State, A: SecretAllocator> private::Sealed for SecretString<L, A> {}
+impl<L: State, A: SecretAllocator> SecretSafe for SecretString<L, A> {}
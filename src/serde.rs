@@ -0,0 +1,182 @@
+//! [`serde`](::serde) support for secret containers, enabled by the `serde` feature.
+//!
+//! Deserializing a `SecretBox<[u8]>` or a `SecretBox<str>` copies the visited
+//! bytes straight from the deserializer into secret memory, without going
+//! through an intermediate `Vec<u8>` or `String`.
+//!
+//! Secrets are never serialized implicitly: `SecretBox<T>` only implements
+//! `Serialize` when `T` implements [`SerializableSecret`], while single fields
+//! can opt in with `#[serde(serialize_with = "secret_mem::serde::serialize_exposed")]`.
+
+use core::{fmt, str};
+
+use ::serde::{
+    de::{self, Deserialize, Deserializer, SeqAccess, Visitor},
+    ser::{Serialize, Serializer},
+};
+
+use crate::{
+    alloc::{Platform, SecretAllocator},
+    marker::{Readable, SecretSafe},
+    SecretBox, SecretVec,
+};
+
+/// Marker trait for values whose secret containers may be serialized.
+///
+/// Implement this trait for your own types to opt into `Serialize` for
+/// `SecretBox<T>`, once exposing them to a serializer is acceptable.
+pub trait SerializableSecret: Serialize {}
+
+impl<T, L, A> Serialize for SecretBox<T, L, A>
+where
+    T: ?Sized + SerializableSecret,
+    L: Readable,
+    A: SecretAllocator,
+{
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+/// Serializes the contents of a secret container, whatever its type.
+///
+/// Intended to be used with `#[serde(serialize_with = "...")]`, to expose
+/// a single secret field on purpose.
+#[inline]
+pub fn serialize_exposed<T, L, A, S>(
+    secret: &SecretBox<T, L, A>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    T: ?Sized + Serialize,
+    L: Readable,
+    A: SecretAllocator,
+    S: Serializer,
+{
+    (**secret).serialize(serializer)
+}
+
+/// Deserializes the value on the stack before moving it into secret memory.
+///
+/// Prefer `SecretBox<[u8]>` or `SecretBox<str>` for byte and string secrets,
+/// which are copied directly into secret memory.
+impl<'de, T: Deserialize<'de> + SecretSafe> Deserialize<'de> for SecretBox<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = T::deserialize(deserializer)?;
+        SecretBox::try_new(value).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretBox<[u8]> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_bytes(SecretBytesVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for SecretBox<str> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(SecretStrVisitor)
+    }
+}
+
+/// Visitor copying bytes straight into a `SecretBox<[u8]>`.
+struct SecretBytesVisitor;
+
+impl<'de> Visitor<'de> for SecretBytesVisitor {
+    type Value = SecretBox<[u8]>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a byte array")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        SecretBox::try_from_slice_copy(v).map_err(E::custom)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.visit_bytes(v.as_bytes())
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Self::Value, S::Error> {
+        // Bound the preallocation, as the size hint is not trusted
+        let capacity = seq.size_hint().unwrap_or(0).min(4096);
+        let mut bytes = SecretVec::try_with_capacity(capacity).map_err(de::Error::custom)?;
+
+        while let Some(byte) = seq.next_element()? {
+            bytes.try_push(byte).map_err(de::Error::custom)?;
+        }
+
+        self.visit_bytes(&bytes)
+    }
+}
+
+/// Visitor copying strings straight into a `SecretBox<str>`.
+struct SecretStrVisitor;
+
+impl<'de> Visitor<'de> for SecretStrVisitor {
+    type Value = SecretBox<str>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        SecretBox::try_from_str_in(v, Platform).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        // The bytes are not echoed back in the error, as they may be secret
+        match str::from_utf8(v) {
+            Ok(s) => self.visit_str(s),
+            Err(_) => Err(E::invalid_value(
+                de::Unexpected::Other("invalid UTF-8"),
+                &self,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::serde::de::{
+        value::{BytesDeserializer, Error, SeqDeserializer, StrDeserializer, U64Deserializer},
+        IntoDeserializer,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize_bytes() {
+        let secret = SecretBox::<[u8]>::deserialize(BytesDeserializer::<Error>::new(b"secret"))
+            .expect("Failed to deserialize SecretBox<[u8]>");
+        assert_eq!(&*secret, b"secret");
+
+        let seq = SeqDeserializer::<_, Error>::new([1u8, 2, 3].into_iter());
+        let secret =
+            SecretBox::<[u8]>::deserialize(seq).expect("Failed to deserialize SecretBox<[u8]>");
+        assert_eq!(&*secret, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_deserialize_str() {
+        let secret = SecretBox::<str>::deserialize(StrDeserializer::<Error>::new("password"))
+            .expect("Failed to deserialize SecretBox<str>");
+        assert_eq!(&*secret, "password");
+
+        let invalid = BytesDeserializer::<Error>::new(b"\xFF");
+        assert!(SecretBox::<str>::deserialize(invalid).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_value() {
+        let secret = SecretBox::<u64>::deserialize(U64Deserializer::<Error>::new(42))
+            .expect("Failed to deserialize SecretBox<u64>");
+        assert_eq!(*secret, 42);
+
+        let invalid = IntoDeserializer::<Error>::into_deserializer("42");
+        assert!(SecretBox::<u64>::deserialize(invalid).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::io;
+
+use crate::alloc::AllocatorKind;
+
+/// Secret memory usage statistics, returned by [`stats`].
+///
+/// The statistics are broken down by backend, each one counting the
+/// allocations made by its allocator, whether through the platform allocator
+/// or an instance created directly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Stats {
+    /// The statistics of the `memfd_secret` backend.
+    pub memfd_secret: BackendStats,
+    /// The statistics of the `mlock` backend.
+    pub mlock: BackendStats,
+    /// The statistics of the `VirtualLock` backend.
+    pub virtual_lock: BackendStats,
+}
+
+/// Secret memory usage statistics of a single backend.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BackendStats {
+    /// The number of live allocations.
+    pub allocations: usize,
+    /// The bytes requested by the live allocations.
+    pub requested_bytes: usize,
+    /// The bytes mapped for the live allocations, guard pages included.
+    pub mapped_bytes: usize,
+    /// The bytes of the pages holding the live allocations, which are
+    /// locked into RAM (see [`MemlockBudget::locked`](crate::alloc::MemlockBudget::locked)).
+    pub locked_bytes: usize,
+    /// The highest value reached by `mapped_bytes`.
+    pub peak_mapped_bytes: usize,
+    /// The number of failed allocations.
+    pub alloc_failures: u64,
+    /// The number of allocations whose pages could not be locked into RAM,
+    /// and were kept unlocked under [`MlockFailure::Warn`](crate::alloc::MlockFailure::Warn).
+    pub unlocked_allocs: u64,
+    /// The number of successful transitions to a read-only protection.
+    pub locks: u64,
+    /// The number of failed transitions to a read-only protection.
+    pub lock_failures: u64,
+    /// The number of successful transitions to a writable protection.
+    pub unlocks: u64,
+    /// The number of failed transitions to a writable protection.
+    pub unlock_failures: u64,
+    /// The number of successful transitions to an inaccessible protection.
+    pub seals: u64,
+    /// The number of failed transitions to an inaccessible protection.
+    pub seal_failures: u64,
+}
+
+impl Stats {
+    /// Returns the statistics of the backend of the given allocator kind,
+    /// or `None` for [`AllocatorKind::Unavailable`].
+    pub fn backend(&self, kind: AllocatorKind) -> Option<&BackendStats> {
+        match kind {
+            AllocatorKind::MemfdSecret => Some(&self.memfd_secret),
+            AllocatorKind::Mlock => Some(&self.mlock),
+            AllocatorKind::VirtualLock => Some(&self.virtual_lock),
+            AllocatorKind::Unavailable => None,
+        }
+    }
+}
+
+impl BackendStats {
+    /// Returns the bytes mapped on top of those requested, which are lost
+    /// to page granularity and guard pages.
+    #[inline]
+    pub fn overhead_bytes(&self) -> usize {
+        self.mapped_bytes.saturating_sub(self.requested_bytes)
+    }
+}
+
+/// Returns the secret memory usage statistics of the process.
+///
+/// The counters are updated independently, so a snapshot taken while other
+/// threads allocate may be slightly inconsistent.
+pub fn stats() -> Stats {
+    Stats {
+        memfd_secret: MEMFD_SECRET.snapshot(),
+        mlock: MLOCK.snapshot(),
+        virtual_lock: VIRTUAL_LOCK.snapshot(),
+    }
+}
+
+/// The live counters of a backend.
+pub(crate) struct Counters {
+    allocations: AtomicUsize,
+    requested_bytes: AtomicUsize,
+    mapped_bytes: AtomicUsize,
+    locked_bytes: AtomicUsize,
+    peak_mapped_bytes: AtomicUsize,
+    alloc_failures: AtomicU64,
+    unlocked_allocs: AtomicU64,
+    locks: AtomicU64,
+    lock_failures: AtomicU64,
+    unlocks: AtomicU64,
+    unlock_failures: AtomicU64,
+    seals: AtomicU64,
+    seal_failures: AtomicU64,
+}
+
+/// The counters of the `memfd_secret` backend.
+pub(crate) static MEMFD_SECRET: Counters = Counters::new();
+/// The counters of the `mlock` backend.
+pub(crate) static MLOCK: Counters = Counters::new();
+/// The counters of the `VirtualLock` backend.
+pub(crate) static VIRTUAL_LOCK: Counters = Counters::new();
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            allocations: AtomicUsize::new(0),
+            requested_bytes: AtomicUsize::new(0),
+            mapped_bytes: AtomicUsize::new(0),
+            locked_bytes: AtomicUsize::new(0),
+            peak_mapped_bytes: AtomicUsize::new(0),
+            alloc_failures: AtomicU64::new(0),
+            unlocked_allocs: AtomicU64::new(0),
+            locks: AtomicU64::new(0),
+            lock_failures: AtomicU64::new(0),
+            unlocks: AtomicU64::new(0),
+            unlock_failures: AtomicU64::new(0),
+            seals: AtomicU64::new(0),
+            seal_failures: AtomicU64::new(0),
+        }
+    }
+
+    /// Records the outcome of an allocation.
+    pub(crate) fn record_alloc<T>(
+        &self,
+        result: &io::Result<T>,
+        requested: usize,
+        locked: usize,
+        mapped: usize,
+    ) {
+        if result.is_err() {
+            self.alloc_failures.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        self.requested_bytes.fetch_add(requested, Ordering::Relaxed);
+        self.locked_bytes.fetch_add(locked, Ordering::Relaxed);
+
+        let mapped_bytes = self.mapped_bytes.fetch_add(mapped, Ordering::Relaxed) + mapped;
+        self.peak_mapped_bytes
+            .fetch_max(mapped_bytes, Ordering::Relaxed);
+    }
+
+    /// Records a successful deallocation.
+    pub(crate) fn record_dealloc(&self, requested: usize, locked: usize, mapped: usize) {
+        self.allocations.fetch_sub(1, Ordering::Relaxed);
+        self.requested_bytes.fetch_sub(requested, Ordering::Relaxed);
+        self.locked_bytes.fetch_sub(locked, Ordering::Relaxed);
+        self.mapped_bytes.fetch_sub(mapped, Ordering::Relaxed);
+    }
+
+    /// Records an allocation whose pages are kept unlocked.
+    pub(crate) fn record_unlocked(&self) {
+        self.unlocked_allocs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records the outcome of a transition to a read-only protection.
+    pub(crate) fn record_lock(&self, result: &io::Result<()>) {
+        match result {
+            Ok(_) => self.locks.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.lock_failures.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    /// Records the outcome of a transition to a writable protection.
+    pub(crate) fn record_unlock(&self, result: &io::Result<()>) {
+        match result {
+            Ok(_) => self.unlocks.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.unlock_failures.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    /// Records the outcome of a transition to an inaccessible protection.
+    pub(crate) fn record_seal(&self, result: &io::Result<()>) {
+        match result {
+            Ok(_) => self.seals.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.seal_failures.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    fn snapshot(&self) -> BackendStats {
+        BackendStats {
+            allocations: self.allocations.load(Ordering::Relaxed),
+            requested_bytes: self.requested_bytes.load(Ordering::Relaxed),
+            mapped_bytes: self.mapped_bytes.load(Ordering::Relaxed),
+            locked_bytes: self.locked_bytes.load(Ordering::Relaxed),
+            peak_mapped_bytes: self.peak_mapped_bytes.load(Ordering::Relaxed),
+            alloc_failures: self.alloc_failures.load(Ordering::Relaxed),
+            unlocked_allocs: self.unlocked_allocs.load(Ordering::Relaxed),
+            locks: self.locks.load(Ordering::Relaxed),
+            lock_failures: self.lock_failures.load(Ordering::Relaxed),
+            unlocks: self.unlocks.load(Ordering::Relaxed),
+            unlock_failures: self.unlock_failures.load(Ordering::Relaxed),
+            seals: self.seals.load(Ordering::Relaxed),
+            seal_failures: self.seal_failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counters() {
+        let counters = Counters::new();
+
+        counters.record_alloc(&Ok(()), 32, 4096, 12288);
+        counters.record_alloc(&Ok(()), 64, 4096, 4096);
+        counters.record_alloc(
+            &io::Result::<()>::Err(io::ErrorKind::OutOfMemory.into()),
+            8,
+            0,
+            0,
+        );
+        counters.record_unlocked();
+        counters.record_lock(&Ok(()));
+        counters.record_unlock(&Err(io::ErrorKind::PermissionDenied.into()));
+        counters.record_seal(&Ok(()));
+        counters.record_dealloc(32, 4096, 12288);
+
+        let stats = counters.snapshot();
+        assert_eq!(stats.allocations, 1);
+        assert_eq!(stats.requested_bytes, 64);
+        assert_eq!(stats.mapped_bytes, 4096);
+        assert_eq!(stats.locked_bytes, 4096);
+        assert_eq!(stats.peak_mapped_bytes, 16384);
+        assert_eq!(stats.overhead_bytes(), 4032);
+        assert_eq!(stats.alloc_failures, 1);
+        assert_eq!(stats.unlocked_allocs, 1);
+        assert_eq!((stats.locks, stats.lock_failures), (1, 0));
+        assert_eq!((stats.unlocks, stats.unlock_failures), (0, 1));
+        assert_eq!((stats.seals, stats.seal_failures), (1, 0));
+    }
+
+    #[test]
+    fn test_stats() {
+        let kind = crate::capabilities().allocator;
+        let before = stats();
+
+        let secret = crate::SecretBox::new([0u8; 32]);
+        let secret = secret.lock().expect("Failed to lock SecretBox");
+
+        // Other tests allocate concurrently, only lower bounds hold
+        let after = stats();
+        let before = before.backend(kind).expect("No statistics for the backend");
+        let after = after.backend(kind).expect("No statistics for the backend");
+        assert!(after.allocations >= 1);
+        assert!(after.peak_mapped_bytes >= crate::alloc::page_size());
+        assert!(after.locks > before.locks);
+
+        drop(secret);
+    }
+}
@@ -0,0 +1,620 @@
+use core::{
+    fmt,
+    ops::{Deref, DerefMut},
+    str::{self, FromStr},
+};
+
+use crate::{
+    alloc::{Platform, SecretAllocator},
+    error::{Error, LockError, UnlockError},
+    marker::{Locked, NoAccess, Readable, State, Unlocked},
+    util::ct,
+    vec::SecretVec,
+};
+
+/// A growable secure container for storing secret UTF-8 strings.
+///
+/// Unlike a `SecretBox<String>`, which only protects the string header,
+/// the characters themselves are stored in memory obtained from the
+/// [`SecretAllocator`] `A`, which defaults to the [`Platform`] allocator.
+///
+/// Equality between `SecretString`s is evaluated in constant time.
+pub struct SecretString<L: State = Unlocked, A: SecretAllocator = Platform> {
+    vec: SecretVec<u8, L, A>,
+}
+
+impl SecretString<Unlocked> {
+    /// Creates a new, empty `SecretString`.
+    ///
+    /// The string will not allocate until characters are pushed onto it.
+    #[inline]
+    pub fn new() -> Self {
+        Self::new_in(Platform)
+    }
+
+    /// Creates a new, empty `SecretString` with at least the specified capacity.
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretString::try_with_capacity`] for a fallible alternative.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_in(capacity, Platform)
+    }
+
+    /// Attempts to create a new, empty `SecretString` with at least the specified capacity.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, Error> {
+        Self::try_with_capacity_in(capacity, Platform)
+    }
+}
+
+impl<A: SecretAllocator> SecretString<Unlocked, A> {
+    /// Creates a new, empty `SecretString` which allocates its memory with
+    /// the given allocator.
+    ///
+    /// The string will not allocate until characters are pushed onto it.
+    #[inline]
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            vec: SecretVec::new_in(alloc),
+        }
+    }
+
+    /// Creates a new, empty `SecretString` with at least the specified capacity,
+    /// allocating its memory with the given allocator.
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretString::try_with_capacity_in`] for a fallible alternative.
+    #[inline]
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        Self {
+            vec: SecretVec::with_capacity_in(capacity, alloc),
+        }
+    }
+
+    /// Attempts to create a new, empty `SecretString` with at least the specified
+    /// capacity, allocating its memory with the given allocator.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_with_capacity_in(capacity: usize, alloc: A) -> Result<Self, Error> {
+        SecretVec::try_with_capacity_in(capacity, alloc).map(|vec| Self { vec })
+    }
+
+    /// Reserves capacity for at least `additional` more bytes.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretString::try_reserve`]
+    /// for a fallible alternative.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.vec.reserve(additional)
+    }
+
+    /// Attempts to reserve capacity for at least `additional` more bytes.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), Error> {
+        self.vec.try_reserve(additional)
+    }
+
+    /// Appends the given `char` to the end of the string.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretString::try_push`]
+    /// for a fallible alternative.
+    pub fn push(&mut self, ch: char) {
+        self.try_push(ch).expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to append the given `char` to the end of the string.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    pub fn try_push(&mut self, ch: char) -> Result<(), Error> {
+        let mut buf = [0u8; 4];
+        let result = self.try_push_str(ch.encode_utf8(&mut buf));
+
+        zeroize::Zeroize::zeroize(&mut buf);
+        result
+    }
+
+    /// Appends the given string slice to the end of the string.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretString::try_push_str`]
+    /// for a fallible alternative.
+    pub fn push_str(&mut self, string: &str) {
+        self.try_push_str(string)
+            .expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to append the given string slice to the end of the string.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_push_str(&mut self, string: &str) -> Result<(), Error> {
+        self.vec.try_extend_from_slice(string.as_bytes())
+    }
+
+    /// Removes the last character from the string and returns it, or `None`
+    /// if it is empty.
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.as_str().chars().next_back()?;
+        self.vec.truncate(self.len() - ch.len_utf8());
+        Some(ch)
+    }
+
+    /// Shortens the string to the specified length, in bytes.
+    ///
+    /// If `new_len` is greater than the current length, this has no effect.
+    /// The capacity of the string is left unchanged.
+    ///
+    /// # Panics
+    /// Panics if `new_len` does not lie on a `char` boundary.
+    pub fn truncate(&mut self, new_len: usize) {
+        if new_len < self.len() {
+            assert!(
+                self.as_str().is_char_boundary(new_len),
+                "new_len does not lie on a char boundary"
+            );
+            self.vec.truncate(new_len)
+        }
+    }
+
+    /// Truncates the string, removing all contents.
+    ///
+    /// The capacity of the string is left unchanged.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.vec.clear()
+    }
+
+    /// Returns a mutable string slice of the entire string.
+    #[inline]
+    pub fn as_mut_str(&mut self) -> &mut str {
+        self
+    }
+
+    /// Applies the Unicode Normalization Form KC (NFKC) to the string.
+    ///
+    /// This is commonly required before hashing or comparing passwords
+    /// (e.g. by RFC 8265). The characters are decomposed, reordered and
+    /// recomposed within a buffer allocated from the same allocator, then
+    /// written back into the string, so that no character ever reaches the
+    /// normal heap.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    /// In this case the string is left unchanged.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn normalize_nfkc(&mut self) -> Result<(), Error>
+    where
+        A: Clone,
+    {
+        use unicode_normalization::{char::decompose_compatible, is_nfkc_quick, IsNormalized};
+
+        if is_nfkc_quick(self.as_str().chars()) == IsNormalized::Yes {
+            return Ok(());
+        }
+
+        let alloc = SecretVec::allocator(&self.vec).clone();
+        let mut chars = SecretVec::<char, Unlocked, A>::try_with_capacity_in(self.len(), alloc)?;
+
+        let mut result = Ok(());
+        for ch in self.as_str().chars() {
+            decompose_compatible(ch, |ch| {
+                if result.is_ok() {
+                    result = chars.try_push(ch);
+                }
+            });
+        }
+        result?;
+
+        nfkc::reorder(&mut chars);
+        let len = nfkc::compose(&mut chars);
+        chars.truncate(len);
+
+        let len = chars.iter().map(|ch| ch.len_utf8()).sum::<usize>();
+        self.vec.try_reserve(len.saturating_sub(self.len()))?;
+
+        self.vec.clear();
+        for &ch in chars.iter() {
+            self.try_push(ch)?;
+        }
+
+        Ok(())
+    }
+
+    /// Locks the `SecretString`, making its contents read-only.
+    ///
+    /// If successful, returns a `SecretString` in the `Locked` state,
+    /// preventing further modifications.
+    ///
+    /// # Errors
+    /// Returns a [`LockError`] carrying the original `SecretString` and the
+    /// cause if the memory cannot be made read-only.
+    pub fn lock(self) -> Result<SecretString<Locked, A>, LockError<Self>> {
+        match self.vec.lock() {
+            Ok(vec) => Ok(SecretString { vec }),
+            Err(e) => {
+                let (vec, source) = e.into_parts();
+                Err(LockError::new(Self { vec }, source))
+            }
+        }
+    }
+
+    /// Seals the `SecretString`, making its contents inaccessible.
+    ///
+    /// If successful, returns a `SecretString` in the `NoAccess` state,
+    /// where any access to the contents faults.
+    ///
+    /// # Errors
+    /// Returns a [`LockError`] carrying the original `SecretString` and the
+    /// cause if the memory cannot be made inaccessible.
+    pub fn seal(self) -> Result<SecretString<NoAccess, A>, LockError<Self>> {
+        match self.vec.seal() {
+            Ok(vec) => Ok(SecretString { vec }),
+            Err(e) => {
+                let (vec, source) = e.into_parts();
+                Err(LockError::new(Self { vec }, source))
+            }
+        }
+    }
+}
+
+impl<A: SecretAllocator> SecretString<Locked, A> {
+    /// Unlocks the `SecretString`, allowing modifications to its contents.
+    ///
+    /// If successful, returns a `SecretString` in the `Unlocked` state.
+    ///
+    /// # Errors
+    /// Returns an [`UnlockError`] carrying the original `SecretString` and the
+    /// cause if the memory cannot be made writable.
+    pub fn unlock(self) -> Result<SecretString<Unlocked, A>, UnlockError<Self>> {
+        match self.vec.unlock() {
+            Ok(vec) => Ok(SecretString { vec }),
+            Err(e) => {
+                let (vec, source) = e.into_parts();
+                Err(UnlockError::new(Self { vec }, source))
+            }
+        }
+    }
+
+    /// Seals the `SecretString`, making its contents inaccessible.
+    ///
+    /// If successful, returns a `SecretString` in the `NoAccess` state,
+    /// where any access to the contents faults.
+    ///
+    /// # Errors
+    /// Returns a [`LockError`] carrying the original `SecretString` and the
+    /// cause if the memory cannot be made inaccessible.
+    pub fn seal(self) -> Result<SecretString<NoAccess, A>, LockError<Self>> {
+        match self.vec.seal() {
+            Ok(vec) => Ok(SecretString { vec }),
+            Err(e) => {
+                let (vec, source) = e.into_parts();
+                Err(LockError::new(Self { vec }, source))
+            }
+        }
+    }
+}
+
+impl<A: SecretAllocator> SecretString<NoAccess, A> {
+    /// Unseals the `SecretString`, making its contents readable again.
+    ///
+    /// If successful, returns a `SecretString` in the `Locked` state.
+    ///
+    /// # Errors
+    /// Returns an [`UnlockError`] carrying the original `SecretString` and the
+    /// cause if the memory cannot be made read-only.
+    pub fn unseal(self) -> Result<SecretString<Locked, A>, UnlockError<Self>> {
+        match self.vec.unseal() {
+            Ok(vec) => Ok(SecretString { vec }),
+            Err(e) => {
+                let (vec, source) = e.into_parts();
+                Err(UnlockError::new(Self { vec }, source))
+            }
+        }
+    }
+
+    /// Unlocks the `SecretString`, allowing modifications to its contents.
+    ///
+    /// If successful, returns a `SecretString` in the `Unlocked` state.
+    ///
+    /// # Errors
+    /// Returns an [`UnlockError`] carrying the original `SecretString` and the
+    /// cause if the memory cannot be made writable.
+    pub fn unlock(self) -> Result<SecretString<Unlocked, A>, UnlockError<Self>> {
+        match self.vec.unlock() {
+            Ok(vec) => Ok(SecretString { vec }),
+            Err(e) => {
+                let (vec, source) = e.into_parts();
+                Err(UnlockError::new(Self { vec }, source))
+            }
+        }
+    }
+}
+
+impl<L: State, A: SecretAllocator> SecretString<L, A> {
+    /// Returns the length of the string, in bytes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    /// Returns `true` if the string has a length of zero.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    /// Returns the capacity of the string, in bytes.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+}
+
+impl<L: Readable, A: SecretAllocator> SecretString<L, A> {
+    /// Returns a string slice of the entire string.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        self
+    }
+
+    /// Compares two strings in constant time with respect to their contents.
+    ///
+    /// The running time only depends on the length of the strings.
+    #[inline]
+    pub fn ct_eq<M: Readable, B: SecretAllocator>(&self, other: &SecretString<M, B>) -> bool {
+        ct::ct_eq(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<L: Readable, A: SecretAllocator> PartialEq for SecretString<L, A> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other)
+    }
+}
+
+impl<L: Readable, A: SecretAllocator> Eq for SecretString<L, A> {}
+
+impl<L: Readable, A: SecretAllocator> AsRef<str> for SecretString<L, A> {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl<L: Readable, A: SecretAllocator> AsRef<[u8]> for SecretString<L, A> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<A: SecretAllocator> AsMut<str> for SecretString<Unlocked, A> {
+    #[inline]
+    fn as_mut(&mut self) -> &mut str {
+        self
+    }
+}
+
+impl<L: Readable, A: SecretAllocator> Deref for SecretString<L, A> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        // The contents are only ever written from valid `str`s
+        unsafe { str::from_utf8_unchecked(&self.vec) }
+    }
+}
+
+impl<A: SecretAllocator> DerefMut for SecretString<Unlocked, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // The contents are only ever written from valid `str`s
+        unsafe { str::from_utf8_unchecked_mut(&mut self.vec) }
+    }
+}
+
+impl<A: SecretAllocator> fmt::Write for SecretString<Unlocked, A> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.try_push_str(s).map_err(|_| fmt::Error)
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.try_push(c).map_err(|_| fmt::Error)
+    }
+}
+
+impl<L: State, A: SecretAllocator> fmt::Debug for SecretString<L, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretString").finish_non_exhaustive()
+    }
+}
+
+impl Default for SecretString<Unlocked> {
+    #[inline]
+    fn default() -> Self {
+        SecretString::new()
+    }
+}
+
+impl From<&str> for SecretString<Unlocked> {
+    /// Creates a new `SecretString` containing a copy of the given string slice.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretString::from_str`]
+    /// for a fallible alternative.
+    fn from(s: &str) -> Self {
+        let mut this = SecretString::with_capacity(s.len());
+        this.push_str(s);
+        this
+    }
+}
+
+impl FromStr for SecretString<Unlocked> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut this = SecretString::try_with_capacity(s.len())?;
+        this.try_push_str(s)?;
+        Ok(this)
+    }
+}
+
+/// The canonical reordering and composition steps of NFKC, applied in place
+/// to fully decomposed characters.
+#[cfg(feature = "unicode-normalization")]
+mod nfkc {
+    use unicode_normalization::char::{canonical_combining_class as ccc, compose as compose_pair};
+
+    /// Sorts every sequence of combining marks by canonical combining class,
+    /// keeping the order of marks of the same class.
+    pub fn reorder(chars: &mut [char]) {
+        for i in 1..chars.len() {
+            let class = ccc(chars[i]);
+            if class == 0 {
+                continue;
+            }
+
+            let mut j = i;
+            while j > 0 && ccc(chars[j - 1]) > class {
+                chars.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+    }
+
+    /// Applies the canonical composition algorithm, returning the number of
+    /// characters left at the front of `chars`.
+    pub fn compose(chars: &mut [char]) -> usize {
+        let Some(&first) = chars.first() else {
+            return 0;
+        };
+
+        let (mut starter, mut starter_pos) = (first, 0);
+        // A leading combining mark has no starter to compose with
+        let mut last_class = match ccc(first) {
+            0 => 0,
+            _ => u16::MAX,
+        };
+
+        let mut len = 1;
+        for i in 1..chars.len() {
+            let ch = chars[i];
+            let class = u16::from(ccc(ch));
+
+            match compose_pair(starter, ch) {
+                Some(composed) if last_class < class || last_class == 0 => {
+                    chars[starter_pos] = composed;
+                    starter = composed;
+                }
+                _ => {
+                    if class == 0 {
+                        (starter, starter_pos) = (ch, len);
+                    }
+                    last_class = class;
+                    chars[len] = ch;
+                    len += 1;
+                }
+            }
+        }
+
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::fmt::Write as _;
+
+    use super::*;
+
+    #[test]
+    fn test_secretstring_push() {
+        let mut secret = SecretString::new();
+        secret.push_str("super_secret");
+        secret.push('_');
+        secret.push('🔑');
+
+        assert_eq!(secret.as_str(), "super_secret_🔑");
+        assert_eq!(secret.pop(), Some('🔑'));
+        assert_eq!(secret.pop(), Some('_'));
+        assert_eq!(secret.as_str(), "super_secret");
+    }
+
+    #[test]
+    fn test_secretstring_truncate() {
+        let mut secret = SecretString::from("super_secret_password");
+        secret.truncate(12);
+        assert_eq!(secret.as_str(), "super_secret");
+
+        secret.clear();
+        assert!(secret.is_empty(), "Cleared SecretString should be empty");
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_secretstring_truncate_char_boundary() {
+        let mut secret = SecretString::from("🔑");
+        secret.truncate(1);
+    }
+
+    #[test]
+    fn test_secretstring_write() {
+        let mut secret = SecretString::new();
+        write!(secret, "token-{}", 42).expect("Failed to write into SecretString");
+        assert_eq!(secret.as_str(), "token-42");
+    }
+
+    #[test]
+    fn test_secretstring_eq() {
+        let secret1 = SecretString::from("super_secret_password");
+        let secret2 = SecretString::from("super_secret_password");
+        let secret3 = SecretString::from("super_secret_passwore");
+
+        assert_eq!(secret1, secret2, "Equal SecretStrings should be equal");
+        assert_ne!(
+            secret1, secret3,
+            "Different SecretStrings should not be equal"
+        );
+
+        let locked_secret = secret2.lock().expect("Failed to lock SecretString");
+        assert!(secret1.ct_eq(&locked_secret));
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-normalization")]
+    fn test_secretstring_normalize_nfkc() {
+        let mut secret = SecretString::from("ｐａｓｓ\u{2163}");
+        secret
+            .normalize_nfkc()
+            .expect("Failed to normalize SecretString");
+        assert_eq!(secret.as_str(), "passIV");
+
+        use unicode_normalization::UnicodeNormalization;
+
+        // Long runs of combining marks, Hangul jamo and blocked compositions
+        let marks = "\u{301}\u{316}\u{302}".repeat(20);
+        for input in [
+            format!("e{marks}"),
+            "\u{1100}\u{1161}\u{11A8}".to_string(),
+            "\u{0041}\u{0308}\u{0301}\u{FB01}".to_string(),
+            "\u{0301}a\u{0328}\u{0301}".to_string(),
+            "\u{FDFA}".to_string(),
+        ] {
+            let mut secret = SecretString::from(input.as_str());
+            secret
+                .normalize_nfkc()
+                .expect("Failed to normalize SecretString");
+            assert_eq!(secret.as_str(), input.nfkc().collect::<String>());
+        }
+    }
+}
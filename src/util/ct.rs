@@ -0,0 +1,140 @@
+use core::hint;
+
+/// Equality comparison whose running time does not depend on the
+/// contents of the compared values.
+///
+/// The running time may still depend on the length of dynamically sized
+/// values, which is not considered secret.
+pub trait ConstantTimeEq {
+    /// Returns `true` if `self` and `other` are equal, in constant time.
+    fn ct_eq(&self, other: &Self) -> bool;
+}
+
+macro_rules! impl_ct_eq_for_int {
+    ($($t:ty),* $(,)?) => {$(
+        impl ConstantTimeEq for $t {
+            #[inline]
+            fn ct_eq(&self, other: &Self) -> bool {
+                ct_eq(&self.to_ne_bytes(), &other.to_ne_bytes())
+            }
+        }
+    )*};
+}
+
+impl_ct_eq_for_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl ConstantTimeEq for bool {
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> bool {
+        (*self as u8).ct_eq(&(*other as u8))
+    }
+}
+
+impl ConstantTimeEq for char {
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> bool {
+        (*self as u32).ct_eq(&(*other as u32))
+    }
+}
+
+impl ConstantTimeEq for str {
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> bool {
+        ct_eq(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl<T: ConstantTimeEq> ConstantTimeEq for [T] {
+    fn ct_eq(&self, other: &Self) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+
+        let diff = self.iter().zip(other).fold(0u8, |acc, (x, y)| {
+            hint::black_box(acc | (!x.ct_eq(y) as u8))
+        });
+
+        hint::black_box(diff) == 0
+    }
+}
+
+impl<T: ConstantTimeEq, const N: usize> ConstantTimeEq for [T; N] {
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> bool {
+        self[..].ct_eq(&other[..])
+    }
+}
+
+/// Compares two byte slices in constant time with respect to their contents.
+///
+/// The running time only depends on the length of the slices, which is
+/// not considered secret.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let diff = a
+        .iter()
+        .zip(b)
+        .fold(0u8, |acc, (x, y)| hint::black_box(acc | (x ^ y)));
+
+    hint::black_box(diff) == 0
+}
+
+/// Checks whether a byte slice only contains zeros, in constant time with
+/// respect to its contents.
+pub fn ct_is_zero(a: &[u8]) -> bool {
+    let acc = a.iter().fold(0u8, |acc, x| hint::black_box(acc | x));
+
+    hint::black_box(acc) == 0
+}
+
+/// Copies `src` into `dst` if `choice` is `true`, leaving `dst` untouched
+/// otherwise, in constant time with respect to `choice` and the contents.
+///
+/// # Panics
+/// Panics if the two slices have different lengths.
+pub fn ct_select(dst: &mut [u8], src: &[u8], choice: bool) {
+    assert_eq!(dst.len(), src.len(), "Slices must have the same length");
+
+    // 0x00 keeps `dst`, 0xFF takes `src`
+    let mask = hint::black_box((choice as u8).wrapping_neg());
+    for (x, y) in dst.iter_mut().zip(src) {
+        *x ^= mask & (*x ^ y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ct_eq() {
+        assert!(ct_eq(b"", b""));
+        assert!(ct_eq(b"secret", b"secret"));
+        assert!(!ct_eq(b"secret", b"secreT"));
+        assert!(!ct_eq(b"secret", b"secret!"));
+
+        assert!([1u32, 2, 3].ct_eq(&[1, 2, 3]));
+        assert!(![1u32, 2, 3].ct_eq(&[1, 2, 4]));
+        assert!("secret".ct_eq("secret"));
+    }
+
+    #[test]
+    fn test_ct_is_zero() {
+        assert!(ct_is_zero(b""));
+        assert!(ct_is_zero(&[0; 32]));
+        assert!(!ct_is_zero(b"\0\0\x01"));
+    }
+
+    #[test]
+    fn test_ct_select() {
+        let mut dst = *b"secret";
+        ct_select(&mut dst, b"public", false);
+        assert_eq!(&dst, b"secret");
+
+        ct_select(&mut dst, b"public", true);
+        assert_eq!(&dst, b"public");
+    }
+}
@@ -0,0 +1,270 @@
+use core::hash::{BuildHasher, Hasher};
+use std::{collections::hash_map::RandomState, sync::OnceLock};
+
+use zeroize::Zeroize;
+
+use super::random;
+use crate::{marker::Locked, SecretBox};
+
+/// The allocator of the per-process SipHash key.
+#[cfg(target_family = "unix")]
+type KeyAllocator = &'static dyn crate::alloc::SecretAllocator;
+#[cfg(not(target_family = "unix"))]
+type KeyAllocator = crate::alloc::Platform;
+
+/// The per-process SipHash key, generated on first use for the lifetime of
+/// the process, in secret memory following the configuration of the
+/// platform allocator.
+///
+/// It is inherited by forked children whatever the configured
+/// [`ForkPolicy`](crate::alloc::ForkPolicy), so that they hash secrets as
+/// their parent does.
+static PROCESS_KEY: OnceLock<ProcessKey> = OnceLock::new();
+
+/// Where the per-process SipHash key is kept.
+enum ProcessKey {
+    /// In read-only secret memory.
+    Secret(SecretBox<[u8; 16], Locked, KeyAllocator>),
+    /// In ordinary memory, if secret memory could not be allocated or locked
+    /// (e.g. once `RLIMIT_MEMLOCK` is reached, or with the `strict-memfd`
+    /// backend where `memfd_secret` is not available).
+    ///
+    /// Hashing must not fail, so the key then only hides the secrets from
+    /// the hasher in use, not from someone able to read the process memory.
+    Plain([u8; 16]),
+}
+
+impl ProcessKey {
+    /// Generates a new key, in secret memory if possible.
+    fn generate() -> Self {
+        let key = key_allocator().ok();
+        let key = key.and_then(|alloc| SecretBox::try_new_in([0; 16], alloc).ok());
+        let key = key.and_then(|mut key| {
+            random::fill_random(&mut *key).ok()?;
+            key.lock().ok()
+        });
+
+        match key {
+            Some(key) => Self::Secret(key),
+            None => Self::Plain(plain_key()),
+        }
+    }
+
+    fn bytes(&self) -> &[u8; 16] {
+        match self {
+            Self::Secret(key) => key,
+            Self::Plain(key) => key,
+        }
+    }
+}
+
+/// Returns the allocator of the per-process SipHash key.
+#[cfg(target_family = "unix")]
+fn key_allocator() -> std::io::Result<KeyAllocator> {
+    crate::alloc::process_secret_allocator()
+}
+
+#[cfg(not(target_family = "unix"))]
+fn key_allocator() -> std::io::Result<KeyAllocator> {
+    Ok(crate::alloc::Platform)
+}
+
+/// Generates a key kept in ordinary memory, falling back to the randomly
+/// keyed hashers of the standard library if the system random number
+/// generator fails.
+fn plain_key() -> [u8; 16] {
+    let mut key = [0; 16];
+    if random::fill_random(&mut key).is_err() {
+        for (i, chunk) in key.chunks_exact_mut(8).enumerate() {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_usize(i);
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+    }
+
+    key
+}
+
+/// Returns the per-process SipHash key.
+fn process_key() -> &'static [u8; 16] {
+    PROCESS_KEY.get_or_init(ProcessKey::generate).bytes()
+}
+
+/// A SipHash-2-4 [`Hasher`] keyed with the per-process secret key, whose
+/// state is zeroized when dropped.
+///
+/// Only the output of this pseudorandom function is ever handed to
+/// the caller's hasher, never the secret itself.
+pub struct KeyedHasher {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    tail: u64,
+    ntail: usize,
+    length: usize,
+}
+
+impl KeyedHasher {
+    /// Creates a new hasher keyed with the per-process secret key.
+    #[inline]
+    pub fn new() -> Self {
+        let key = process_key();
+        let mut k0 = [0u8; 8];
+        let mut k1 = [0u8; 8];
+        k0.copy_from_slice(&key[..8]);
+        k1.copy_from_slice(&key[8..]);
+
+        let mut keys = (u64::from_le_bytes(k0), u64::from_le_bytes(k1));
+        let hasher = Self::with_keys(keys.0, keys.1);
+
+        // The key must not outlive the hasher state on the stack
+        k0.zeroize();
+        k1.zeroize();
+        keys.0.zeroize();
+        keys.1.zeroize();
+        hasher
+    }
+
+    /// Creates a new hasher keyed with the given keys.
+    fn with_keys(k0: u64, k1: u64) -> Self {
+        Self {
+            v0: k0 ^ 0x736f_6d65_7073_6575,
+            v1: k1 ^ 0x646f_7261_6e64_6f6d,
+            v2: k0 ^ 0x6c79_6765_6e65_7261,
+            v3: k1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    /// Absorbs a full 8-byte message block.
+    #[inline]
+    fn compress(&mut self, m: u64) {
+        self.v3 ^= m;
+        Self::rounds(&mut self.v0, &mut self.v1, &mut self.v2, &mut self.v3, 2);
+        self.v0 ^= m;
+    }
+
+    /// Applies `n` SipRounds to the given state.
+    #[inline]
+    fn rounds(v0: &mut u64, v1: &mut u64, v2: &mut u64, v3: &mut u64, n: usize) {
+        for _ in 0..n {
+            *v0 = v0.wrapping_add(*v1);
+            *v1 = v1.rotate_left(13) ^ *v0;
+            *v0 = v0.rotate_left(32);
+            *v2 = v2.wrapping_add(*v3);
+            *v3 = v3.rotate_left(16) ^ *v2;
+            *v0 = v0.wrapping_add(*v3);
+            *v3 = v3.rotate_left(21) ^ *v0;
+            *v2 = v2.wrapping_add(*v1);
+            *v1 = v1.rotate_left(17) ^ *v2;
+            *v2 = v2.rotate_left(32);
+        }
+    }
+}
+
+impl Hasher for KeyedHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.tail |= (byte as u64) << (8 * self.ntail);
+            self.ntail += 1;
+
+            if self.ntail == 8 {
+                let m = self.tail;
+                self.compress(m);
+                self.tail = 0;
+                self.ntail = 0;
+            }
+        }
+
+        self.length = self.length.wrapping_add(bytes.len());
+    }
+
+    fn finish(&self) -> u64 {
+        let (mut v0, mut v1, mut v2, mut v3) = (self.v0, self.v1, self.v2, self.v3);
+        let b = ((self.length as u64 & 0xff) << 56) | self.tail;
+
+        v3 ^= b;
+        Self::rounds(&mut v0, &mut v1, &mut v2, &mut v3, 2);
+        v0 ^= b;
+        v2 ^= 0xff;
+        Self::rounds(&mut v0, &mut v1, &mut v2, &mut v3, 4);
+
+        v0 ^ v1 ^ v2 ^ v3
+    }
+}
+
+impl Drop for KeyedHasher {
+    fn drop(&mut self) {
+        self.v0.zeroize();
+        self.v1.zeroize();
+        self.v2.zeroize();
+        self.v3.zeroize();
+        self.tail.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_siphash_vectors() {
+        // Reference vectors from the SipHash paper (key 00..0f)
+        let (k0, k1) = (0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908);
+
+        let hasher = KeyedHasher::with_keys(k0, k1);
+        assert_eq!(hasher.finish(), 0x726f_db47_dd0e_0e31);
+
+        let mut hasher = KeyedHasher::with_keys(k0, k1);
+        hasher.write(&(0..15).collect::<Vec<u8>>());
+        assert_eq!(hasher.finish(), 0xa129_ca61_49be_45e5);
+    }
+
+    #[test]
+    fn test_keyed_hasher() {
+        let mut hasher1 = KeyedHasher::new();
+        let mut hasher2 = KeyedHasher::new();
+        hasher1.write(b"secret");
+        hasher2.write(b"secret");
+        assert_eq!(hasher1.finish(), hasher2.finish());
+    }
+
+    #[test]
+    fn test_plain_key() {
+        // The fallback key is random as well
+        let key = plain_key();
+        assert_ne!(key, [0; 16]);
+        assert_ne!(key, plain_key());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_keyed_hasher_fork() {
+        // Forking within the multithreaded test harness is not safe
+        if !crate::util::isolate("util::keyed_hash::tests::test_keyed_hasher_fork", &[]) {
+            return;
+        }
+
+        let mut hasher = KeyedHasher::new();
+        hasher.write(b"secret");
+        let expected = hasher.finish();
+
+        // The child inherits the key, whatever the platform fork policy
+        match unsafe { libc::fork() } {
+            0 => unsafe {
+                let mut hasher = KeyedHasher::new();
+                hasher.write(b"secret");
+                libc::_exit((hasher.finish() != expected) as libc::c_int);
+            },
+            pid => {
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                assert!(libc::WIFEXITED(status), "Child should not fault");
+                assert_eq!(libc::WEXITSTATUS(status), 0, "Child hash differs");
+            }
+        }
+    }
+}
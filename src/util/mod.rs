@@ -1,3 +1,106 @@
+pub mod ct;
+#[cfg(not(feature = "no-ord-hash"))]
+pub mod keyed_hash;
+pub mod random;
 mod unique;
 
+use core::{
+    alloc::Layout,
+    ptr::{self, NonNull},
+};
+#[cfg(target_family = "unix")]
+use std::{io, process, sync::OnceLock};
+
+use zeroize::Zeroize;
+
 pub use unique::Unique;
+
+use crate::{alloc::SecretAllocator, error::Error};
+
+/// The id of the process which allocated the first secret container, whose
+/// forked children may not have inherited its memory.
+#[cfg(target_family = "unix")]
+static OWNER: OnceLock<u32> = OnceLock::new();
+
+/// Allocates a memory region for the given `layout`, returning a dangling
+/// (but well-aligned) pointer for zero-sized layouts.
+pub fn allocate<A: SecretAllocator>(alloc: &A, layout: Layout) -> Result<NonNull<u8>, Error> {
+    match layout.size() {
+        0 => Ok(unsafe { NonNull::new_unchecked(layout.align() as *mut u8) }),
+        _ => {
+            #[cfg(target_family = "unix")]
+            OWNER.get_or_init(process::id);
+            alloc.alloc(layout).map_err(Error::from_alloc)
+        }
+    }
+}
+
+/// Returns `true` if the memory region of `len` bytes starting at `ptr` was
+/// left out of this process, a forked child of the one which allocated it
+/// (e.g. under [`ForkPolicy::DontFork`](crate::alloc::ForkPolicy::DontFork)).
+///
+/// Such a region is not mapped, and must not be accessed nor deallocated.
+#[cfg(target_family = "unix")]
+pub fn is_left_out(ptr: NonNull<u8>, len: usize) -> bool {
+    if len == 0 || OWNER.get().map_or(true, |&owner| owner == process::id()) {
+        return false;
+    }
+
+    // `msync` reports unmapped pages, whatever their protection
+    let start = ptr.as_ptr() as usize & !(crate::alloc::page_size() - 1);
+    let size = ptr.as_ptr() as usize + len - start;
+    let result = unsafe { libc::msync(start as *mut libc::c_void, size, libc::MS_ASYNC) };
+    result < 0 && io::Error::last_os_error().raw_os_error() == Some(libc::ENOMEM)
+}
+
+/// Returns `true` if the memory region of `len` bytes starting at `ptr` was
+/// left out of this process, a forked child of the one which allocated it.
+///
+/// Processes are not forked on this platform.
+#[cfg(not(target_family = "unix"))]
+#[inline]
+pub fn is_left_out(_ptr: NonNull<u8>, _len: usize) -> bool {
+    false
+}
+
+/// Runs the test named `test` again in a process of its own, with the given
+/// environment variables, for tests which change process-wide state.
+///
+/// Returns `true` within that process, where the test goes on, and `false`
+/// in the calling process once the other one has passed.
+///
+/// # Panics
+/// Panics if the test fails in the other process.
+#[cfg(test)]
+pub fn isolate(test: &str, envs: &[(&str, &str)]) -> bool {
+    const ISOLATED_ENV: &str = "SECRET_MEM_TEST_ISOLATED";
+    if std::env::var_os(ISOLATED_ENV).is_some_and(|isolated| isolated == test) {
+        return true;
+    }
+
+    let output = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", test, "--nocapture"])
+        .env(ISOLATED_ENV, test)
+        .envs(envs.iter().copied())
+        .output()
+        .expect("Failed to run the test process");
+    assert!(
+        output.status.success(),
+        "Test process failed: {}\n{}{}",
+        output.status,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    false
+}
+
+/// Overwrites the `len` bytes starting at `ptr` with zeros, in a way that
+/// is not optimized away by the compiler.
+///
+/// # Safety
+/// `ptr` must be valid for writes of `len` bytes.
+pub unsafe fn wipe(ptr: NonNull<u8>, len: usize) {
+    let bytes_slice = ptr::slice_from_raw_parts_mut(ptr.as_ptr(), len);
+    unsafe { &mut *bytes_slice }.zeroize();
+}
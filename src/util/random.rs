@@ -0,0 +1,75 @@
+use std::io;
+
+/// Fills `buf` with cryptographically secure random bytes obtained from
+/// the operating system.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn fill_random(buf: &mut [u8]) -> io::Result<()> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        let remaining = &mut buf[filled..];
+        match unsafe { libc::getrandom(remaining.as_mut_ptr() as _, remaining.len(), 0) } {
+            -1 => match io::Error::last_os_error() {
+                error if error.kind() == io::ErrorKind::Interrupted => continue,
+                error => return Err(error),
+            },
+            read => filled += read as usize,
+        }
+    }
+
+    Ok(())
+}
+
+/// Fills `buf` with cryptographically secure random bytes obtained from
+/// the operating system.
+#[cfg(all(
+    target_family = "unix",
+    not(any(target_os = "linux", target_os = "android"))
+))]
+pub fn fill_random(buf: &mut [u8]) -> io::Result<()> {
+    unsafe { libc::arc4random_buf(buf.as_mut_ptr() as _, buf.len()) };
+    Ok(())
+}
+
+/// Fills `buf` with cryptographically secure random bytes obtained from
+/// the operating system.
+#[cfg(target_family = "windows")]
+pub fn fill_random(buf: &mut [u8]) -> io::Result<()> {
+    use core::ptr;
+    use windows_sys::Win32::{
+        Foundation::RtlNtStatusToDosError,
+        Security::Cryptography::{BCryptGenRandom, BCRYPT_USE_SYSTEM_PREFERRED_RNG},
+    };
+
+    for chunk in buf.chunks_mut(u32::MAX as usize) {
+        let status = unsafe {
+            BCryptGenRandom(
+                ptr::null_mut(),
+                chunk.as_mut_ptr(),
+                chunk.len() as u32,
+                BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+            )
+        };
+
+        if status < 0 {
+            let code = unsafe { RtlNtStatusToDosError(status) };
+            return Err(io::Error::from_raw_os_error(code as i32));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_random() {
+        let mut buf1 = [0u8; 32];
+        let mut buf2 = [0u8; 32];
+        fill_random(&mut buf1).expect("Failed to obtain random bytes");
+        fill_random(&mut buf2).expect("Failed to obtain random bytes");
+        assert_ne!(buf1, buf2, "Random buffers should differ");
+    }
+}
@@ -38,15 +38,6 @@ impl<T: Sized> Unique<T> {
 }
 
 impl<T: ?Sized> Unique<T> {
-    /// Creates a new `Unique` if `ptr` is non-null.
-    #[inline]
-    pub fn new(ptr: *mut T) -> Option<Self> {
-        NonNull::new(ptr).map(|pointer| Unique {
-            pointer,
-            _marker: PhantomData,
-        })
-    }
-
     /// Creates a new `Unique` without checking if the pointer is null.
     ///
     /// # Safety
@@ -66,16 +57,10 @@ impl<T: ?Sized> Unique<T> {
         self.pointer.as_ptr()
     }
 
-    /// Casts to a pointer of another type.
-    ///
-    /// The cast is done with the assumption that the underlying
-    /// memory is valid for the new type.
+    /// Acquires the underlying `NonNull` pointer.
     #[inline]
-    pub const fn cast<U>(self) -> Unique<U> {
-        Unique {
-            pointer: self.pointer.cast(),
-            _marker: PhantomData,
-        }
+    pub const fn as_non_null_ptr(self) -> NonNull<T> {
+        self.pointer
     }
 }
 
@@ -100,6 +85,16 @@ impl<T: ?Sized> Clone for Unique<T> {
     }
 }
 
+impl<T: ?Sized> From<NonNull<T>> for Unique<T> {
+    #[inline]
+    fn from(pointer: NonNull<T>) -> Self {
+        Unique {
+            pointer,
+            _marker: PhantomData,
+        }
+    }
+}
+
 impl<T: ?Sized> fmt::Pointer for Unique<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Pointer::fmt(&self.as_ptr(), f)
@@ -0,0 +1,728 @@
+use core::{
+    alloc::Layout,
+    cmp, fmt,
+    marker::PhantomData,
+    mem::{self, ManuallyDrop},
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    slice,
+};
+use std::io;
+
+use crate::{
+    alloc::{Platform, SecretAllocator},
+    error::{Error, LockError, UnlockError},
+    marker::{Locked, NoAccess, Readable, SecretSafe, State, Unlocked},
+    util::{
+        self,
+        ct::{self, ConstantTimeEq},
+        Unique,
+    },
+};
+
+/// A growable secure container for storing secret values.
+///
+/// This structure is designed for secrets that are built incrementally,
+/// such as keys read chunk by chunk from a file or tokens assembled from
+/// several parts.
+///
+/// The elements are stored in memory obtained from the [`SecretAllocator`] `A`,
+/// which defaults to the [`Platform`] allocator. When the vector grows, its
+/// contents are copied into a new secret region and the old one is zeroized
+/// and released through [`SecretAllocator::dealloc`].
+pub struct SecretVec<T, L: State = Unlocked, A: SecretAllocator = Platform> {
+    pointer: Unique<T>,
+    capacity: usize,
+    len: usize,
+    alloc: A,
+    _marker: PhantomData<L>,
+}
+
+impl<T: SecretSafe> SecretVec<T, Unlocked> {
+    /// Creates a new, empty `SecretVec`.
+    ///
+    /// The vector will not allocate until elements are pushed onto it.
+    #[inline]
+    pub fn new() -> Self {
+        Self::new_in(Platform)
+    }
+
+    /// Creates a new, empty `SecretVec` with at least the specified capacity.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretVec::try_with_capacity`]
+    /// for a fallible alternative.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_in(capacity, Platform)
+    }
+
+    /// Attempts to create a new, empty `SecretVec` with at least the specified capacity.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    #[inline]
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, Error> {
+        Self::try_with_capacity_in(capacity, Platform)
+    }
+}
+
+impl<T> SecretVec<T, Unlocked> {
+    /// Creates a new, empty `SecretVec` whose elements are not required to
+    /// implement [`SecretSafe`].
+    ///
+    /// **Warning**: only the inline part of the elements is stored in secret
+    /// memory. Any heap memory owned by an element (e.g. the bytes of a
+    /// `String`) stays on the global heap, where it is neither locked nor
+    /// zeroized.
+    #[inline]
+    pub fn new_shallow() -> Self {
+        Self::new_shallow_in(Platform)
+    }
+}
+
+impl<T: SecretSafe, A: SecretAllocator> SecretVec<T, Unlocked, A> {
+    /// Creates a new, empty `SecretVec` which allocates its memory with
+    /// the given allocator.
+    ///
+    /// The vector will not allocate until elements are pushed onto it.
+    #[inline]
+    pub fn new_in(alloc: A) -> Self {
+        Self::new_shallow_in(alloc)
+    }
+
+    /// Creates a new, empty `SecretVec` with at least the specified capacity,
+    /// allocating its memory with the given allocator.
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretVec::try_with_capacity_in`] for a fallible alternative.
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        Self::try_with_capacity_in(capacity, alloc).expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to create a new, empty `SecretVec` with at least the specified
+    /// capacity, allocating its memory with the given allocator.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    pub fn try_with_capacity_in(capacity: usize, alloc: A) -> Result<Self, Error> {
+        let mut this = Self::new_in(alloc);
+        this.try_reserve(capacity)?;
+        Ok(this)
+    }
+}
+
+impl<T, A: SecretAllocator> SecretVec<T, Unlocked, A> {
+    /// Creates a new, empty `SecretVec` whose elements are not required to
+    /// implement [`SecretSafe`], allocating its memory with the given
+    /// allocator.
+    ///
+    /// **Warning**: see [`SecretVec::new_shallow`].
+    #[inline]
+    pub fn new_shallow_in(alloc: A) -> Self {
+        Self {
+            pointer: Unique::dangling(),
+            capacity: if mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                0
+            },
+            len: 0,
+            alloc,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Reserves capacity for at least `additional` more elements.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretVec::try_reserve`]
+    /// for a fallible alternative.
+    pub fn reserve(&mut self, additional: usize) {
+        self.try_reserve(additional)
+            .expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to reserve capacity for at least `additional` more elements.
+    ///
+    /// When the current capacity is not enough, the elements are moved into
+    /// a new secret memory region, and the old region is zeroized and released.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    /// In this case the vector is left unchanged. If the old region cannot be
+    /// released (e.g. its canary does not match), returns
+    /// [`Error::ReleaseFailed`] once the elements have been moved.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), Error> {
+        let required = self
+            .len
+            .checked_add(additional)
+            .ok_or_else(capacity_overflow)?;
+        if required <= self.capacity {
+            return Ok(());
+        }
+
+        let capacity = cmp::max(cmp::max(self.capacity * 2, required), 8);
+        self.grow(capacity)
+    }
+
+    /// Appends an element to the back of the vector.
+    ///
+    /// Panics if the memory allocation fails, see [`SecretVec::try_push`]
+    /// for a fallible alternative.
+    pub fn push(&mut self, value: T) {
+        self.try_push(value)
+            .expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to append an element to the back of the vector.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    pub fn try_push(&mut self, value: T) -> Result<(), Error> {
+        self.try_reserve(1)?;
+        unsafe { ptr::write(self.pointer.as_ptr().add(self.len), value) };
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes the last element from the vector and returns it, or `None`
+    /// if it is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        match self.len {
+            0 => None,
+            _ => {
+                self.len -= 1;
+                Some(unsafe { ptr::read(self.pointer.as_ptr().add(self.len)) })
+            }
+        }
+    }
+
+    /// Shortens the vector, keeping the first `len` elements and dropping
+    /// the rest.
+    ///
+    /// If `len` is greater than the current length, this has no effect.
+    /// The capacity of the vector is left unchanged.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+
+        let tail = ptr::slice_from_raw_parts_mut(
+            unsafe { self.pointer.as_ptr().add(len) },
+            self.len - len,
+        );
+
+        self.len = len;
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    /// Clears the vector, removing all values.
+    ///
+    /// The capacity of the vector is left unchanged.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    /// Returns a mutable slice of the entire vector.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self
+    }
+
+    /// Locks the `SecretVec`, making its contents read-only.
+    ///
+    /// If successful, returns a `SecretVec` in the `Locked` state,
+    /// preventing further modifications.
+    ///
+    /// # Errors
+    /// Returns a [`LockError`] carrying the original `SecretVec` and the
+    /// cause if the memory cannot be made read-only.
+    pub fn lock(self) -> Result<SecretVec<T, Locked, A>, LockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_read_only(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(LockError::new(self, e)),
+        }
+    }
+
+    /// Seals the `SecretVec`, making its contents inaccessible.
+    ///
+    /// If successful, returns a `SecretVec` in the `NoAccess` state,
+    /// where any access to the contents faults.
+    ///
+    /// # Errors
+    /// Returns a [`LockError`] carrying the original `SecretVec` and the
+    /// cause if the memory cannot be made inaccessible.
+    pub fn seal(self) -> Result<SecretVec<T, NoAccess, A>, LockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_no_access(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(LockError::new(self, e)),
+        }
+    }
+
+    /// Moves the elements into a new secret memory region of the given
+    /// capacity, releasing the old one.
+    fn grow(&mut self, capacity: usize) -> Result<(), Error> {
+        let layout = Layout::array::<T>(capacity).map_err(|_| capacity_overflow())?;
+        let pointer = util::allocate(&self.alloc, layout)?.cast::<T>();
+
+        unsafe { ptr::copy_nonoverlapping(self.pointer.as_ptr(), pointer.as_ptr(), self.len) };
+
+        // The elements live in the new region even if the old one cannot be
+        // released, which is reported once the vector is consistent again
+        let released = self.protect(|alloc, ptr, layout| unsafe { alloc.dealloc(ptr, layout) });
+        self.pointer = Unique::from(pointer);
+        self.capacity = capacity;
+        released.map_err(Error::ReleaseFailed)
+    }
+}
+
+impl<T, A: SecretAllocator> SecretVec<T, Locked, A> {
+    /// Unlocks the `SecretVec`, allowing modifications to its contents.
+    ///
+    /// If successful, returns a `SecretVec` in the `Unlocked` state.
+    ///
+    /// # Errors
+    /// Returns an [`UnlockError`] carrying the original `SecretVec` and the
+    /// cause if the memory cannot be made writable.
+    pub fn unlock(self) -> Result<SecretVec<T, Unlocked, A>, UnlockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_writable(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(UnlockError::new(self, e)),
+        }
+    }
+
+    /// Seals the `SecretVec`, making its contents inaccessible.
+    ///
+    /// If successful, returns a `SecretVec` in the `NoAccess` state,
+    /// where any access to the contents faults.
+    ///
+    /// # Errors
+    /// Returns a [`LockError`] carrying the original `SecretVec` and the
+    /// cause if the memory cannot be made inaccessible.
+    pub fn seal(self) -> Result<SecretVec<T, NoAccess, A>, LockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_no_access(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(LockError::new(self, e)),
+        }
+    }
+}
+
+impl<T, A: SecretAllocator> SecretVec<T, NoAccess, A> {
+    /// Unseals the `SecretVec`, making its contents readable again.
+    ///
+    /// If successful, returns a `SecretVec` in the `Locked` state.
+    ///
+    /// # Errors
+    /// Returns an [`UnlockError`] carrying the original `SecretVec` and the
+    /// cause if the memory cannot be made read-only.
+    pub fn unseal(self) -> Result<SecretVec<T, Locked, A>, UnlockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_read_only(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(UnlockError::new(self, e)),
+        }
+    }
+
+    /// Unlocks the `SecretVec`, allowing modifications to its contents.
+    ///
+    /// If successful, returns a `SecretVec` in the `Unlocked` state.
+    ///
+    /// # Errors
+    /// Returns an [`UnlockError`] carrying the original `SecretVec` and the
+    /// cause if the memory cannot be made writable.
+    pub fn unlock(self) -> Result<SecretVec<T, Unlocked, A>, UnlockError<Self>> {
+        match self.protect(|alloc, ptr, layout| unsafe { alloc.make_writable(ptr, layout) }) {
+            Ok(_) => Ok(self.into_state()),
+            Err(e) => Err(UnlockError::new(self, e)),
+        }
+    }
+}
+
+impl<T, L: State, A: SecretAllocator> SecretVec<T, L, A> {
+    /// Returns the number of elements in the vector.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the vector contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of elements the vector can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns a reference to the underlying allocator.
+    ///
+    /// Note: this is an associated function, which means that you have
+    /// to call it as `SecretVec::allocator(&v)` instead of `v.allocator()`.
+    #[inline]
+    pub fn allocator(this: &Self) -> &A {
+        &this.alloc
+    }
+
+    /// Returns the layout of the allocated memory region.
+    #[inline]
+    fn layout(&self) -> Layout {
+        match mem::size_of::<T>() {
+            0 => Layout::new::<()>(),
+            size => unsafe {
+                Layout::from_size_align_unchecked(size * self.capacity, mem::align_of::<T>())
+            },
+        }
+    }
+
+    /// Applies a protection change to the allocated memory region, if any.
+    fn protect<F>(&self, f: F) -> io::Result<()>
+    where
+        F: FnOnce(&A, NonNull<u8>, Layout) -> io::Result<()>,
+    {
+        match self.layout() {
+            layout if layout.size() == 0 => Ok(()),
+            layout => f(&self.alloc, self.pointer.as_non_null_ptr().cast(), layout),
+        }
+    }
+
+    /// Moves the pointer and the allocator into a `SecretVec` with
+    /// a different state marker, without running `Drop`.
+    fn into_state<M: State>(self) -> SecretVec<T, M, A> {
+        let this = ManuallyDrop::new(self);
+
+        SecretVec {
+            pointer: this.pointer,
+            capacity: this.capacity,
+            len: this.len,
+            alloc: unsafe { ptr::read(&this.alloc) },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Clone, A: SecretAllocator> SecretVec<T, Unlocked, A> {
+    /// Clones and appends all the elements of a slice to the vector.
+    ///
+    /// Panics if the memory allocation fails, see
+    /// [`SecretVec::try_extend_from_slice`] for a fallible alternative.
+    pub fn extend_from_slice(&mut self, other: &[T]) {
+        self.try_extend_from_slice(other)
+            .expect("Unable to allocate secret memory")
+    }
+
+    /// Attempts to clone and append all the elements of a slice to the vector.
+    ///
+    /// # Errors
+    /// Returns an [`Error`] describing the cause if the memory allocation fails.
+    /// In this case the vector is left unchanged.
+    pub fn try_extend_from_slice(&mut self, other: &[T]) -> Result<(), Error> {
+        self.try_reserve(other.len())?;
+        for value in other {
+            unsafe { ptr::write(self.pointer.as_ptr().add(self.len), value.clone()) };
+            self.len += 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T, L: Readable, A: SecretAllocator> SecretVec<T, L, A> {
+    /// Returns a slice of the entire vector.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T: ConstantTimeEq, L: Readable, A: SecretAllocator> SecretVec<T, L, A> {
+    /// Compares the elements with `other` in constant time.
+    ///
+    /// The running time only depends on the length of the slices.
+    #[inline]
+    pub fn ct_eq(&self, other: &[T]) -> bool {
+        self.as_slice().ct_eq(other)
+    }
+}
+
+impl<L: Readable, A: SecretAllocator> SecretVec<u8, L, A> {
+    /// Checks whether the vector only contains zero bytes, in constant time.
+    #[inline]
+    pub fn ct_is_zero(&self) -> bool {
+        ct::ct_is_zero(self)
+    }
+}
+
+impl<A: SecretAllocator> SecretVec<u8, Unlocked, A> {
+    /// Replaces the contents with the bytes of `other` if `choice` is `true`,
+    /// in constant time with respect to both `choice` and the contents.
+    ///
+    /// # Panics
+    /// Panics if `other` does not have the same length as the vector.
+    #[inline]
+    pub fn ct_select(&mut self, other: &[u8], choice: bool) {
+        ct::ct_select(self, other, choice);
+    }
+}
+
+#[cfg(not(feature = "ct-eq"))]
+impl<T: PartialEq, L: Readable, A: SecretAllocator> PartialEq for SecretVec<T, L, A> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        PartialEq::eq(&**self, &**other)
+    }
+}
+
+#[cfg(not(feature = "ct-eq"))]
+impl<T: Eq, L: Readable, A: SecretAllocator> Eq for SecretVec<T, L, A> {}
+
+#[cfg(feature = "ct-eq")]
+impl<T: ConstantTimeEq, L: Readable, A: SecretAllocator> PartialEq for SecretVec<T, L, A> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other)
+    }
+}
+
+#[cfg(feature = "ct-eq")]
+impl<T: ConstantTimeEq, L: Readable, A: SecretAllocator> Eq for SecretVec<T, L, A> {}
+
+impl<T, L: Readable, A: SecretAllocator> AsRef<[T]> for SecretVec<T, L, A> {
+    #[inline]
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, A: SecretAllocator> AsMut<[T]> for SecretVec<T, Unlocked, A> {
+    #[inline]
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, L: Readable, A: SecretAllocator> Deref for SecretVec<T, L, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { slice::from_raw_parts(self.pointer.as_ptr(), self.len) }
+    }
+}
+
+impl<T, A: SecretAllocator> DerefMut for SecretVec<T, Unlocked, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { slice::from_raw_parts_mut(self.pointer.as_ptr(), self.len) }
+    }
+}
+
+impl<T, L: State, A: SecretAllocator> fmt::Debug for SecretVec<T, L, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretVec").finish_non_exhaustive()
+    }
+}
+
+impl<T: SecretSafe> Default for SecretVec<T, Unlocked> {
+    #[inline]
+    fn default() -> Self {
+        SecretVec::new()
+    }
+}
+
+impl<T, L: State, A: SecretAllocator> Drop for SecretVec<T, L, A> {
+    fn drop(&mut self) {
+        // The memory of a parent process may be missing from a forked child,
+        // where wiping it would fault
+        if util::is_left_out(self.pointer.as_non_null_ptr().cast(), self.layout().size()) {
+            return;
+        }
+
+        // Make the memory writable unless it already is, since the destructors
+        // of the elements may write to it. If this fails the elements are
+        // leaked, as dropping them in place could fault.
+        let writable = match L::WRITABLE {
+            true => Ok(()),
+            false => self.protect(|alloc, ptr, layout| unsafe { alloc.make_writable(ptr, layout) }),
+        };
+
+        if writable.is_ok() {
+            // Safely drop the elements in place, then erase what they left behind
+            let elements = ptr::slice_from_raw_parts_mut(self.pointer.as_ptr(), self.len);
+            unsafe { ptr::drop_in_place(elements) };
+            let _ = self.protect(|_, ptr, layout| {
+                unsafe { util::wipe(ptr, layout.size()) };
+                Ok(())
+            });
+        }
+
+        // Deallocate the memory
+        let _ = self.protect(|alloc, ptr, layout| unsafe { alloc.dealloc(ptr, layout) });
+    }
+}
+
+/// Returns the error reported when the requested capacity overflows `isize::MAX` bytes.
+fn capacity_overflow() -> Error {
+    Error::OutOfMemory(io::Error::new(
+        io::ErrorKind::OutOfMemory,
+        "capacity overflow",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secretvec_push() {
+        let mut secret = SecretVec::new();
+        assert!(secret.is_empty(), "New SecretVec should be empty");
+
+        for i in 0..100u32 {
+            secret.push(i);
+        }
+
+        assert_eq!(secret.len(), 100, "SecretVec should contain all values");
+        assert!(
+            secret.iter().copied().eq(0..100),
+            "SecretVec should keep the values in order after growing"
+        );
+
+        assert_eq!(secret.pop(), Some(99));
+        assert_eq!(secret.len(), 99);
+    }
+
+    #[test]
+    fn test_secretvec_new_shallow() {
+        let mut secret = SecretVec::new_shallow();
+        secret.push(String::from("super_secret_password"));
+        assert_eq!(secret[0], "super_secret_password");
+    }
+
+    #[test]
+    fn test_secretvec_extend_from_slice() {
+        let mut secret = SecretVec::with_capacity(4);
+        secret.extend_from_slice(b"super_");
+        secret.extend_from_slice(b"secret_");
+        secret.extend_from_slice(b"password");
+
+        assert_eq!(&*secret, b"super_secret_password");
+    }
+
+    #[test]
+    fn test_secretvec_truncate() {
+        let mut secret = SecretVec::new();
+        secret.extend_from_slice(b"super_secret_password");
+
+        let capacity = secret.capacity();
+        secret.truncate(5);
+        assert_eq!(&*secret, b"super");
+        assert_eq!(
+            secret.capacity(),
+            capacity,
+            "Truncate should keep the capacity"
+        );
+
+        secret.clear();
+        assert!(secret.is_empty(), "Cleared SecretVec should be empty");
+    }
+
+    #[test]
+    fn test_secretvec_reserve() {
+        let mut secret = SecretVec::<u8>::new();
+        secret.reserve(100);
+        assert!(secret.capacity() >= 100);
+        assert!(secret.is_empty());
+    }
+
+    #[test]
+    fn test_secretvec_reserve_release_failure() {
+        use crate::alloc::{Platform, SecretAllocator};
+
+        /// Releases the memory, but reports a failure.
+        struct FailingDealloc;
+
+        unsafe impl SecretAllocator for FailingDealloc {
+            fn alloc(&self, layout: Layout) -> io::Result<NonNull<u8>> {
+                Platform.alloc(layout)
+            }
+
+            unsafe fn make_read_only(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+                Platform.make_read_only(ptr, layout)
+            }
+
+            unsafe fn make_writable(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+                Platform.make_writable(ptr, layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: NonNull<u8>, layout: Layout) -> io::Result<()> {
+                Platform.dealloc(ptr, layout)?;
+                Err(io::ErrorKind::InvalidData.into())
+            }
+        }
+
+        let mut secret = SecretVec::with_capacity_in(4, FailingDealloc);
+        secret.extend_from_slice(b"key");
+
+        let error = secret.try_reserve(100).unwrap_err();
+        assert!(matches!(error, Error::ReleaseFailed(_)));
+        assert!(secret.capacity() >= 103, "The vector should have grown");
+        assert_eq!(&*secret, b"key");
+    }
+
+    #[test]
+    fn test_secretvec_lock() {
+        let mut secret = SecretVec::new();
+        secret.extend_from_slice(b"super_secret_password");
+
+        let locked_secret = secret.lock().expect("Failed to lock SecretVec");
+        assert_eq!(&*locked_secret, b"super_secret_password");
+
+        let mut unlocked_secret = locked_secret.unlock().expect("Failed to unlock SecretVec");
+        unlocked_secret.push(b'!');
+        assert_eq!(&*unlocked_secret, b"super_secret_password!");
+
+        let sealed_secret = unlocked_secret.seal().expect("Failed to seal SecretVec");
+        assert_eq!(sealed_secret.len(), 22);
+
+        let locked_secret = sealed_secret.unseal().expect("Failed to unseal SecretVec");
+        assert_eq!(&*locked_secret, b"super_secret_password!");
+
+        let empty_secret = SecretVec::<u8>::new();
+        let empty_secret = empty_secret.lock().expect("Failed to lock SecretVec");
+        assert!(empty_secret.is_empty());
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_secretvec_drop_forked() {
+        use crate::alloc::{Config, ForkPolicy, UnixSecretAllocator};
+
+        // Forking within the multithreaded test harness is not safe
+        if !crate::util::isolate("vec::tests::test_secretvec_drop_forked", &[]) {
+            return;
+        }
+
+        let config = Config::new().fork_policy(ForkPolicy::DontFork);
+        let alloc = UnixSecretAllocator::with_config(&config);
+        let mut unlocked = SecretVec::new_in(&alloc);
+        unlocked.extend_from_slice(b"super_secret_password");
+        let mut locked = SecretVec::new_in(&alloc);
+        locked.extend_from_slice(b"super_secret_password");
+        let locked = locked.lock().expect("Failed to lock SecretVec");
+
+        // The child drops the vectors it did not inherit
+        match unsafe { libc::fork() } {
+            0 => {
+                drop(unlocked);
+                drop(locked);
+                unsafe { libc::_exit(0) };
+            }
+            pid => {
+                let mut status = 0;
+                assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+                assert!(libc::WIFEXITED(status), "Child should not fault");
+                assert_eq!(libc::WEXITSTATUS(status), 0);
+            }
+        }
+    }
+}